@@ -9,9 +9,37 @@ use std::collections::HashMap;
 use std::fmt::{self, Display, Formatter};
 use std::str::FromStr;
 
+// Options that never take a value, so `tw post --split "a long tweet"` doesn't take the tweet for the value
+const FLAGS: &[&str] = &[
+    "all",
+    "a",
+    "all-profiles",
+    "collate",
+    "debug",
+    "dump",
+    "dumps",
+    "edit",
+    "e",
+    "help",
+    "h",
+    "login",
+    "l",
+    "no-history",
+    "number",
+    "oauth2",
+    "refresh",
+    "split",
+    "super-followers-only",
+    "version",
+    "v",
+    "yes",
+    "y",
+];
+
 #[derive(Debug)]
 pub struct BaseArgs {
-    named: HashMap<String, String>,
+    // In the order they were given, since some can be repeated (e.g. --media)
+    named: Vec<(String, String)>,
     pub positional: Vec<String>,
     pub flags: HashMap<String, bool>,
     is_debug: bool,
@@ -20,30 +48,38 @@ pub struct BaseArgs {
 // simple argument collector
 impl BaseArgs {
     pub fn parse() -> Result<Self, TwitterError> {
-        let args: Vec<String> = std::env::args().collect();
+        Self::from_args(std::env::args().collect())
+    }
+
+    // Everything tw was run with, the executable name first
+    pub fn from_args(args: Vec<String>) -> Result<Self, TwitterError> {
         // Start at 1 to omit the executable name
         let mut i = 1;
-        let mut named = HashMap::new();
+        let mut named = Vec::new();
         let mut flags = HashMap::new();
         let mut positional = Vec::new();
 
         while i < args.len() {
             let arg = &args[i];
-            if arg.starts_with("-") {
+            // A lone dash is the usual "read from stdin", so it's positional
+            if arg.starts_with("-") && arg != "-" {
                 let key = if arg.starts_with("--") {
                     arg.trim_start_matches("--").to_string()
                 } else {
                     arg.trim_start_matches("-").to_string()
                 };
 
-                // If this argument is last in the list, or the next one is also a named arg, do not use next arg as value
-                // ... it might be smarter to just have a list of accepted flags, i.e. debug, help, version
-                let is_flag_arg = i + 1 >= args.len() || args[i + 1].starts_with("-");
+                // If this argument is a known flag, last in the list, or the next one is also a named arg, do not use
+                // next arg as value. A dash followed by a digit is a negative number (or UTC offset, e.g. -05:00), so
+                // that's a value
+                let is_flag_arg = FLAGS.contains(&key.as_str())
+                    || i + 1 >= args.len()
+                    || (args[i + 1].starts_with("-") && !is_negative_number(&args[i + 1]));
                 if is_flag_arg {
                     flags.insert(key, true);
                     i += 1;
                 } else {
-                    named.insert(key, args[i + 1].to_string());
+                    named.push((key, args[i + 1].to_string()));
                     i += 2;
                 };
             } else {
@@ -53,7 +89,7 @@ impl BaseArgs {
         }
 
         // I'm absolutely SURE this is bad practice but I don't care
-        let is_debug = flags.get("debug").unwrap_or(&false).clone();
+        let is_debug = *flags.get("debug").unwrap_or(&false);
 
         Ok(Self {
             named,
@@ -63,44 +99,39 @@ impl BaseArgs {
         })
     }
 
+    // The last value given wins, and the long name wins over the short one
+    fn value(&self, long_name: &str, short_name: &str) -> Option<&String> {
+        let last = |name: &str| {
+            self.named
+                .iter()
+                .rev()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value)
+        };
+        last(long_name).or_else(|| last(short_name))
+    }
+
     pub fn get<T: FromStr>(&self, long_name: &str, short_name: &str, default: T) -> T {
-        match self.named.get(long_name) {
-            Some(thing) => match thing.parse::<T>() {
-                Ok(val) => val,
-                Err(_err) => default,
-            },
-            None => match self.named.get(short_name) {
-                Some(thing) => match thing.parse::<T>() {
-                    Ok(val) => val,
-                    Err(_err) => default,
-                },
-                None => default,
-            },
-        }
+        self.get_option(long_name, short_name).unwrap_or(default)
     }
 
     pub fn get_option<T: FromStr>(&self, long_name: &str, short_name: &str) -> Option<T> {
-        match self.named.get(long_name) {
-            Some(thing) => match thing.parse::<T>() {
-                Ok(val) => Some(val),
-                Err(_err) => None,
-            },
-            None => match self.named.get(short_name) {
-                Some(thing) => match thing.parse::<T>() {
-                    Ok(val) => Some(val),
-                    Err(_err) => None,
-                },
-                None => None,
-            },
-        }
+        self.value(long_name, short_name)
+            .and_then(|thing| thing.parse::<T>().ok())
+    }
+
+    // Every value of an option that can be given more than once, in order
+    pub fn get_all(&self, long_name: &str, short_name: &str) -> Vec<String> {
+        self.named
+            .iter()
+            .filter(|(key, _)| key == long_name || key == short_name)
+            .map(|(_, value)| value.to_string())
+            .collect()
     }
 
     pub fn get_position<T: FromStr>(&self, position: usize) -> Option<T> {
         match self.positional.get(position) {
-            Some(thing) => match thing.parse::<T>() {
-                Ok(val) => Some(val),
-                Err(_err) => None,
-            },
+            Some(thing) => thing.parse::<T>().ok(),
             None => None,
         }
     }
@@ -120,10 +151,12 @@ impl BaseArgs {
             Some(arg) => arg == "help" || arg == "h",
             None => false,
         };
-        let is_help_flag_set = self.flags.get("help").is_some()
-            || self.flags.get("h").is_some()
-            || self.named.get("help").is_some()
-            || self.named.get("h").is_some();
+        let is_help_flag_set = self.flags.contains_key("help")
+            || self.flags.contains_key("h")
+            || self
+                .named
+                .iter()
+                .any(|(key, _)| key == "help" || key == "h");
         last_positional_arg_is_help || is_help_flag_set
     }
 
@@ -134,22 +167,75 @@ impl BaseArgs {
     }
 }
 
+fn is_negative_number(arg: &str) -> bool {
+    arg.strip_prefix("-")
+        .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+}
+
 impl Display for BaseArgs {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         let named = self
             .named
             .iter()
             .fold(Vec::<String>::new(), |vec, (k, v)| {
-                [&vec[..], &vec![format!("{}: {}", k, v)]].concat()
+                [&vec[..], &[format!("{}: {}", k, v)]].concat()
             })
             .join(", ");
         let positional = self
             .positional
             .iter()
             .fold(Vec::<String>::new(), |vec, string| {
-                [&vec[..], &vec![format!("{}", string)]].concat()
+                [&vec[..], &[string.to_string()]].concat()
             })
             .join(", ");
         write!(f, "BaseArgs <[{}] {}>", positional, named)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_args(args: &[&str]) -> BaseArgs {
+        BaseArgs::from_args(args.iter().map(|arg| arg.to_string()).collect()).unwrap()
+    }
+
+    #[test]
+    fn flags_and_values() {
+        let args = from_args(&[
+            "tw",
+            "post",
+            "--no-history",
+            "hello",
+            "--split",
+            "-e",
+            "world",
+        ]);
+        assert_eq!(args.positional, vec!["post", "hello", "world"]);
+        assert!(args.get_flag("no-history", "no-history"));
+        assert!(args.get_flag("split", "split"));
+        assert!(args.get_flag("edit", "e"));
+
+        let args = from_args(&[
+            "tw",
+            "stats",
+            "--utc-offset",
+            "-05:00",
+            "--top",
+            "3",
+            "--debug",
+        ]);
+        assert_eq!(
+            args.get_option::<String>("utc-offset", "utc-offset")
+                .unwrap(),
+            "-05:00"
+        );
+        assert_eq!(args.get::<usize>("top", "top", 5), 3);
+        assert!(args.get_flag("debug", "debug"));
+
+        let args = from_args(&["tw", "post", "-", "--reply-id", "--quote", "20"]);
+        assert_eq!(args.positional, vec!["post", "-"]);
+        assert!(args.get_flag("reply-id", "r"));
+        assert_eq!(args.get_option::<String>("quote", "q").unwrap(), "20");
+    }
+}
@@ -0,0 +1,200 @@
+// ~/.twitter_cache.toml, for things fetched from Twitter that are worth remembering between runs.
+// Use TW_CACHE to point somewhere else. Safe to delete, everything in it gets fetched again.
+//
+// Every entry carries a fingerprint of whatever it was fetched with (e.g. a profile's tokens), and is ignored once
+// that changes, so switching a profile to another account or rotating keys never serves up stale data.
+//
+// [me.default]
+// fingerprint = "3f2a..."
+// id = "1234"
+// username = "jack"
+
+use super::error::TwitterError;
+use super::private;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use toml::value::Table;
+use toml::Value;
+
+pub fn path() -> PathBuf {
+    let file = env::var("TW_CACHE").unwrap_or_else(|_| String::from(".twitter_cache.toml"));
+    let mut path = home::home_dir().expect("Cannot get your home directory!");
+    path.push(file);
+    path
+}
+
+// A cache that can't be read is as good as an empty one
+fn read() -> Table {
+    match fs::read_to_string(path()) {
+        Ok(contents) => toml::from_str(&contents).unwrap_or_default(),
+        Err(_) => Table::new(),
+    }
+}
+
+// Only readable by you, since some of what goes in here works as a password
+fn write(table: &Table) -> Result<(), TwitterError> {
+    private::write(&path(), toml::to_string(table)?)?;
+    Ok(())
+}
+
+// Hex SHA-256 of the parts. Never the parts themselves, some of them are secrets
+pub fn fingerprint(parts: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part.as_bytes());
+        // So ["ab", "c"] and ["a", "bc"] don't come out the same
+        hasher.update([0]);
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+pub fn get<T: DeserializeOwned>(section: &str, key: &str, fingerprint: &str) -> Option<T> {
+    lookup(&read(), section, key, fingerprint)
+}
+
+pub fn set<T: Serialize>(
+    section: &str,
+    key: &str,
+    fingerprint: &str,
+    value: &T,
+) -> Result<(), TwitterError> {
+    let mut table = read();
+    insert(&mut table, section, key, fingerprint, value)?;
+    write(&table)
+}
+
+pub fn remove(section: &str, key: &str) -> Result<(), TwitterError> {
+    let mut table = read();
+    match delete(&mut table, section, key) {
+        true => write(&table),
+        false => Ok(()),
+    }
+}
+
+// What get, set and remove do once the file has been read, kept apart so they can be tested without one
+fn lookup<T: DeserializeOwned>(
+    table: &Table,
+    section: &str,
+    key: &str,
+    fingerprint: &str,
+) -> Option<T> {
+    let entry = table.get(section)?.get(key)?.clone();
+    if entry.get("fingerprint").and_then(Value::as_str) != Some(fingerprint) {
+        return None;
+    }
+    entry.try_into().ok()
+}
+
+fn insert<T: Serialize>(
+    table: &mut Table,
+    section: &str,
+    key: &str,
+    fingerprint: &str,
+    value: &T,
+) -> Result<(), TwitterError> {
+    let mut entry = match Value::try_from(value)? {
+        Value::Table(entry) => entry,
+        _ => {
+            return Err(TwitterError::Invalid(format!(
+                "Only tables go in the cache, not {}.{}",
+                section, key
+            )))
+        }
+    };
+    entry.insert(
+        String::from("fingerprint"),
+        Value::String(fingerprint.to_string()),
+    );
+    let section = table
+        .entry(section)
+        .or_insert_with(|| Value::Table(Table::new()));
+    if let Value::Table(section) = section {
+        section.insert(key.to_string(), Value::Table(entry));
+    }
+    Ok(())
+}
+
+fn delete(table: &mut Table, section: &str, key: &str) -> bool {
+    match table.get_mut(section) {
+        Some(Value::Table(section)) => section.remove(key).is_some(),
+        _ => false,
+    }
+}
+
+// Whatever goes wrong with the cache, the command itself still worked
+pub fn warn(result: Result<(), TwitterError>) {
+    if let Err(err) = result {
+        eprintln!("Could not update {}: {}", path().display(), err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::twitter::TwitterUser;
+    use super::*;
+
+    fn user(pinned_tweet_id: Option<&str>) -> TwitterUser {
+        serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "username": "jack",
+            "name": "jack",
+            "created_at": "2006-03-21T20:50:14.000Z",
+            "pinned_tweet_id": pinned_tweet_id,
+        }))
+        .unwrap()
+    }
+
+    // Through TOML text and back, the way it goes through the file
+    fn reread(table: &Table) -> Table {
+        toml::from_str(&toml::to_string(table).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn users_round_trip() {
+        for me in [user(Some("20")), user(None)] {
+            let mut table = Table::new();
+            insert(&mut table, "me", "default", "abc", &me).unwrap();
+            let cached: TwitterUser = lookup(&reread(&table), "me", "default", "abc").unwrap();
+            assert_eq!(
+                serde_json::to_value(&cached).unwrap(),
+                serde_json::to_value(&me).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn fingerprints() {
+        let mut table = Table::new();
+        let before = fingerprint(&["https://api.twitter.com", "key", "token"]);
+        let after = fingerprint(&["https://api.twitter.com", "key", "new token"]);
+        assert_ne!(before, after);
+        assert_ne!(fingerprint(&["ab", "c"]), fingerprint(&["a", "bc"]));
+
+        insert(&mut table, "me", "default", &before, &user(None)).unwrap();
+        assert!(lookup::<TwitterUser>(&table, "me", "default", &before).is_some());
+        // The token changed, so whoever is cached may not be who the profile is anymore
+        assert!(lookup::<TwitterUser>(&table, "me", "default", &after).is_none());
+        assert!(lookup::<TwitterUser>(&table, "me", "alt1", &before).is_none());
+        assert!(lookup::<TwitterUser>(&table, "bearer", "default", &before).is_none());
+    }
+
+    #[test]
+    fn removing() {
+        let mut table = Table::new();
+        insert(&mut table, "me", "default", "abc", &user(None)).unwrap();
+        insert(&mut table, "me", "alt1", "abc", &user(None)).unwrap();
+        assert!(delete(&mut table, "me", "default"));
+        assert!(!delete(&mut table, "me", "default"));
+        assert!(!delete(&mut table, "bearer", "default"));
+        assert!(lookup::<TwitterUser>(&table, "me", "default", "abc").is_none());
+        assert!(lookup::<TwitterUser>(&table, "me", "alt1", "abc").is_some());
+    }
+}
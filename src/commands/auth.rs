@@ -0,0 +1,63 @@
+use super::super::args::BaseArgs;
+use super::super::credentials;
+use super::super::error::TwitterError;
+use super::super::twitter;
+use super::profile::mask;
+
+const HELP: &str = "Manage the tokens tw gets for you!\n
+Usage: tw auth invalidate-bearer [OPTIONS]
+
+Subcommands:
+    invalidate-bearer
+        Invalidate the app-only bearer token for your profile's API key, and forget the copy cached in
+        ~/.twitter_cache.toml. Do this when it may have leaked, or before regenerating the key. The next command
+        that needs one fetches a new one.
+
+Options:
+    -p, --profile <name>
+        The name of the profile to use.
+        Must correspond to an entry in your credentials file (~/.twitter_credentials.toml by default).
+    -c, --credentials <name>
+        The file name or path to use for the credentials file.
+        Default: ~/.twitter_credentials.toml
+    --debug
+        Print debug messages.
+
+Examples:
+    Get rid of the bearer token for your alt's app:
+        tw auth invalidate-bearer -p alt1
+";
+
+fn help() -> Result<(), TwitterError> {
+    println!("{}", HELP);
+    Ok(())
+}
+
+fn invalidate_bearer(base_args: &BaseArgs) -> Result<(), TwitterError> {
+    let credentials = credentials::get(base_args)?;
+    base_args.debug(&credentials);
+
+    let token = twitter::Client::new(&credentials, base_args).invalidate_bearer_token()?;
+    println!(
+        "✅ Invalidated bearer token {} for API key {}",
+        mask(&token),
+        mask(&credentials.api_key)
+    );
+    Ok(())
+}
+
+pub fn execute(base_args: &BaseArgs) -> Result<(), TwitterError> {
+    if base_args.is_requesting_help() {
+        return help();
+    }
+    let subcommand = base_args.get_position::<String>(1).unwrap_or_default();
+
+    match subcommand.as_str() {
+        "invalidate-bearer" => invalidate_bearer(base_args),
+        "" => help(),
+        _ => {
+            println!("Unknown subcommand: {}", subcommand);
+            help()
+        }
+    }
+}
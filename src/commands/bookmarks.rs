@@ -0,0 +1,105 @@
+use super::super::args::BaseArgs;
+use super::super::credentials;
+use super::super::error::TwitterError;
+use super::super::output::Format;
+use super::super::twitter;
+
+const HELP: &str = "See the tweets you've bookmarked!\n
+Usage: tw bookmarks [count] [OPTIONS]
+
+Bookmarks are only available with OAuth 2.0, so log in with `tw login --oauth2` first.
+
+Arguments
+    count (default: 10):
+        integer between 1 and 100.
+
+Options:
+    -t, --next-token <token>
+        A continuation token when paginating results
+    --dump
+        Write raw JSON result to a file
+    -o, --output <format>
+        Print json, jsonl or csv instead of the usual human friendly output.
+    --format <template>
+        Print each tweet with a template, e.g. '{author.handle}: {text} ({metrics.likes} likes)',
+        or the name of a template in ~/.twitter_config.toml.
+    -p, --profile <name>
+        The name of the profile to use.
+        Must correspond to an entry in your credentials file (~/.twitter_credentials.toml by default).
+    -c, --credentials <name>
+        The file name or path to use for the credentials file.
+        Default: ~/.twitter_credentials.toml
+    --debug
+        Print debug messages.
+
+Examples:
+    Read your last 10 bookmarks (default):
+        tw bookmarks
+    Read your last 50 bookmarks:
+        tw bookmarks 50
+";
+
+struct Args {
+    count: i32,
+    next_token: Option<String>,
+}
+
+fn parse(args: &BaseArgs) -> Result<Args, TwitterError> {
+    let count = match args.get_position::<String>(1) {
+        Some(count) => count
+            .parse::<i32>()
+            .map_err(|_| TwitterError::Invalid(format!("Count must be a number, not {}", count)))?,
+        None => 10,
+    };
+    let next_token = args.get_option("next-token", "t");
+    Ok(Args { count, next_token })
+}
+
+fn help() -> Result<(), TwitterError> {
+    println!("{}", HELP);
+    Ok(())
+}
+
+pub fn execute(base_args: &BaseArgs) -> Result<(), TwitterError> {
+    if base_args.is_requesting_help() {
+        return help();
+    }
+    let args = parse(base_args)?;
+    let output = Format::parse(base_args)?;
+    if args.count < 1 || args.count > 100 {
+        return Err(TwitterError::Invalid(
+            "Count must be between 1 and 100".to_string(),
+        ));
+    }
+    let credentials = credentials::get(base_args)?;
+    base_args.debug(&credentials);
+
+    let client = twitter::Client::new(&credentials, base_args);
+    let me = client.me()?;
+    let bookmarks = client.bookmarks_v2(&me.id, args.count, args.next_token)?;
+
+    if output.is_human() {
+        for item in bookmarks.data.iter() {
+            item.display();
+        }
+    } else {
+        let records = bookmarks
+            .data
+            .iter()
+            .map(|item| item.record(None))
+            .collect::<Vec<_>>();
+        output.print(&records)?;
+    }
+
+    if let Some(meta) = bookmarks.meta {
+        if let Some(token) = meta.next_token {
+            if output.is_human() {
+                println!("Next page token: {}", token)
+            } else {
+                eprintln!("Next page token: {}", token)
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use super::super::args::BaseArgs;
+use super::super::error::TwitterError;
+use super::super::output::{CountRecord, Format};
+use super::super::twitter::text;
+use super::post;
+
+const HELP: &str = "Check how long a tweet is before posting it!\n
+Usage: tw count message [...replies] [OPTIONS]
+       tw count --file <path> [OPTIONS]
+       tw count - [OPTIONS]
+
+Counts characters the way Twitter does: links are always 23 characters, and emoji and CJK characters count double.
+Takes the same input as `tw post`, so you can check a whole thread (and what --split would do to it) first.
+Exits with an error if anything is over the limit.
+
+Options:
+    -f, --file <path>
+        Read the text from a file, or `-` instead of --file to read it from stdin.
+        Separate the tweets of a thread with a line containing just ---
+    --delimiter <line>
+        Use something other than --- to separate tweets in --file.
+    --split
+        Count what --split would turn the text into.
+    --number
+        Include the 1/n numbering that --number would add.
+    -o, --output <format>
+        Print json, jsonl or csv instead of the usual human friendly output.
+    --format <template>
+        Print each tweet with a template, e.g. '{length}/{max}', or the name of a template in ~/.twitter_config.toml.
+
+Examples:
+    How much room is left?
+        tw count \"Is this too long? 🤔 https://example.com/a/rather/long/link\"
+    Check a thread before posting it:
+        tw count --file thread.md
+";
+
+fn help() -> Result<(), TwitterError> {
+    println!("{}", HELP);
+    Ok(())
+}
+
+pub fn execute(base_args: &BaseArgs) -> Result<(), TwitterError> {
+    if base_args.is_requesting_help() {
+        return help();
+    }
+    let output = Format::parse(base_args)?;
+    let messages = post::messages(base_args)?;
+    let records = messages
+        .iter()
+        .map(|message| {
+            let length = text::length(message);
+            CountRecord {
+                text: message.to_string(),
+                length,
+                max: text::MAX_LENGTH,
+                remaining: text::MAX_LENGTH as i64 - length as i64,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if output.is_human() {
+        for line in text::summary(&messages) {
+            println!("{}", line);
+        }
+    } else {
+        output.print(&records)?;
+    }
+    let problems = text::problems(&messages);
+    match problems.is_empty() {
+        true => Ok(()),
+        false => Err(TwitterError::Invalid(problems.join(", "))),
+    }
+}
@@ -1,12 +1,41 @@
 use super::super::args::BaseArgs;
-use super::super::credentials;
+use super::super::credentials::{self, Credentials};
 use super::super::error::TwitterError;
-use super::super::twitter;
+use super::super::history;
+use super::super::output::{DeleteRecord, Format};
+use super::super::prompt;
+use super::super::twitter::{self, ids};
+use super::home::{start_time, PAGINATION_RETRIES};
+use std::fs;
 
 const HELP: &str = "Delete a tweet!\n
-Usage: tw delete tweet_id [OPTIONS]
+Usage: tw delete <id or url> [OPTIONS]
+       tw delete last [OPTIONS]
+       tw delete --thread <id> [OPTIONS]
+       tw delete --before <date> [OPTIONS]
+       tw delete --from-file <path> [OPTIONS]
+
+Everything except deleting a single tweet by id shows what's about to go and asks first.
+Deleting carries on past tweets that can't be deleted, and lists them at the end.
 
 Options:
+    --thread <id>
+        Delete a thread posted with tw, last tweet first. <id> is any tweet in the thread, see `tw history`.
+    --before <date>
+        Delete every tweet of yours from before <date>, e.g. 2023-01-01, 2023-01-01T12:00:00Z or
+        2023-01-01T14:00:00+02:00 (times with an offset are converted to UTC).
+        Twitter only lets us see your last 3200 tweets, so anything older than that is out of reach.
+    --from-file <path>
+        Delete every tweet in a file, one id or link per line. Blank lines and lines starting with # are skipped.
+    -y, --yes
+        Don't ask for confirmation.
+    --retry <n>
+        Wait out rate limits and retry server errors up to n times.
+        Default: 0 for single tweets, 3 otherwise.
+    -o, --output <format>
+        Print json, jsonl or csv instead of the usual human friendly output.
+    --format <template>
+        Print the result with a template, e.g. '{id} {deleted}', or the name of a template in ~/.twitter_config.toml.
     -p, --profile <name>
         The name of the profile to use.
         Must correspond to an entry in your credentials file (~/.twitter_credentials.toml by default).
@@ -21,22 +50,55 @@ Examples:
         tw delete 12345666
     Delete a tweet from an alt account:
         tw delete 12345666 --profile alt1
+    Take back what you just said:
+        tw delete last
+    Delete a whole thread:
+        tw delete --thread 12345666
+    Spring cleaning:
+        tw delete --before 2023-01-01
 ";
 
+enum Target {
+    One(String),
+    Last,
+    Thread(String),
+    Before(String),
+    FromFile(String),
+}
+
 struct Args {
+    target: Target,
+    yes: bool,
+}
+
+// What's about to be deleted. The text and date are only known for some targets, and only used for the summary
+struct Doomed {
     id: String,
+    created_at: Option<String>,
+    text: Option<String>,
 }
 
 fn parse(args: &BaseArgs) -> Result<Args, TwitterError> {
-    match args.get_position::<String>(1) {
-        // TODO: find latest tweet and delete
-        // Some(id) if *id == "last" => {
-        //     Client::new(credentials).post_v2(&message).unwrap()
-        //     Ok(())
-        // }
-        Some(id) if id != "" => Ok(Args { id }),
-        _ => Err(TwitterError::MissingArgument("id".to_string())),
-    }
+    let thread = args.get_option::<String>("thread", "thread");
+    let before = args.get_option::<String>("before", "before");
+    let from_file = args.get_option::<String>("from-file", "from-file");
+    let target = match (args.get_position::<String>(1), thread, before, from_file) {
+        (Some(id), None, None, None) if id == "last" => Target::Last,
+        (Some(id), None, None, None) if !id.is_empty() => Target::One(ids::tweet_id(&id)?),
+        (None, Some(id), None, None) => Target::Thread(ids::tweet_id(&id)?),
+        (None, None, Some(date), None) => Target::Before(start_time("--before", &date)?),
+        (None, None, None, Some(path)) => Target::FromFile(path),
+        (None, None, None, None) => return Err(TwitterError::MissingArgument("id".to_string())),
+        _ => {
+            return Err(TwitterError::Invalid(
+                "Pick one of an id, last, --thread, --before or --from-file".to_string(),
+            ))
+        }
+    };
+    Ok(Args {
+        target,
+        yes: args.get_flag("yes", "y"),
+    })
 }
 
 fn help() -> Result<(), TwitterError> {
@@ -44,22 +106,292 @@ fn help() -> Result<(), TwitterError> {
     Ok(())
 }
 
-pub fn execute(base_args: &BaseArgs) -> Result<(), TwitterError> {
-    if base_args.is_requesting_help() {
-        return help();
+fn by_id(id: &str) -> Doomed {
+    Doomed {
+        id: id.to_string(),
+        created_at: None,
+        text: None,
     }
-    let args = parse(&base_args)?;
-    let credentials = credentials::get(base_args)?;
-    base_args.debug(&credentials);
+}
 
-    let response = twitter::Client::new(&credentials, base_args).delete_v2(&args.id)?;
-    if response.deleted == true {
-        println!("Deleted tweet id: {}", args.id);
+fn find(
+    base_args: &BaseArgs,
+    client: &twitter::Client,
+    target: &Target,
+) -> Result<Vec<Doomed>, TwitterError> {
+    let doomed = |item: twitter::TwitterHomeItem| Doomed {
+        id: item.id,
+        created_at: Some(item.created_at),
+        text: Some(item.text),
+    };
+    match target {
+        Target::One(id) => Ok(vec![by_id(id)]),
+        Target::Last => {
+            let me = client.me()?;
+            let home = client.home_v2(&me.id, 5, None, None)?;
+            Ok(home.data.into_iter().take(1).map(doomed).collect())
+        }
+        Target::Thread(id) => {
+            let records = history::read(&credentials::profile_name(base_args))?;
+            let root = records
+                .iter()
+                .find(|record| &record.id == id)
+                .map(|record| record.thread_root_id.to_string())
+                .ok_or_else(|| {
+                    TwitterError::Invalid(format!(
+                        "Tweet {} isn't in your history, so there's no telling what thread it's in",
+                        id
+                    ))
+                })?;
+            // Replies first, so the thread never has holes in the middle
+            Ok(records
+                .into_iter()
+                .rev()
+                .filter(|record| record.thread_root_id == root)
+                .map(|record| Doomed {
+                    id: record.id,
+                    created_at: Some(record.posted_at),
+                    text: Some(record.text),
+                })
+                .collect())
+        }
+        Target::Before(before) => {
+            let me = client.me()?;
+            // Both in UTC by now, and only down to the second: v2 timestamps have milliseconds and --before doesn't
+            let before = before.chars().take(19).collect::<String>();
+            let mut found = Vec::new();
+            for page in client.home_pages(&me.id, 100, None, None) {
+                found.extend(
+                    page?
+                        .into_iter()
+                        .filter(|item| {
+                            item.created_at.chars().take(19).collect::<String>() < before
+                        })
+                        .map(doomed),
+                );
+            }
+            Ok(found)
+        }
+        Target::FromFile(path) => read_ids(path, &fs::read_to_string(path)?),
+    }
+}
+
+// One id or link per line of a --from-file file, skipping blank lines and # comments
+fn read_ids(path: &str, text: &str) -> Result<Vec<Doomed>, TwitterError> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| match ids::tweet_id(line) {
+            Ok(id) => Ok(by_id(&id)),
+            Err(TwitterError::Invalid(message)) => Err(TwitterError::Invalid(format!(
+                "Line {} of {}: {}",
+                i + 1,
+                path,
+                message
+            ))),
+            Err(err) => Err(err),
+        })
+        .collect()
+}
+
+fn summarize(doomed: &[Doomed], credentials: &Credentials, base_args: &BaseArgs) {
+    eprintln!(
+        "About to delete {} {} from @{} [{}]:",
+        doomed.len(),
+        if doomed.len() == 1 { "tweet" } else { "tweets" },
+        credentials.handle,
+        credentials::profile_name(base_args)
+    );
+    for tweet in doomed.iter().take(10) {
+        let date = tweet
+            .created_at
+            .as_deref()
+            .map(|created_at| created_at.chars().take(10).collect::<String>())
+            .unwrap_or_default();
+        let text = tweet
+            .text
+            .as_deref()
+            .and_then(|text| text.lines().next())
+            .map(|text| text.chars().take(50).collect::<String>())
+            .unwrap_or_default();
+        let line = format!("    {:<20} {:<10} {}", tweet.id, date, text);
+        eprintln!("{}", line.trim_end());
+    }
+    if doomed.len() > 10 {
+        eprintln!("    ...and {} more", doomed.len() - 10);
+    }
+}
+
+fn delete_one(client: &twitter::Client, output: &Format, id: &str) -> Result<(), TwitterError> {
+    let response = client.delete_v2(id)?;
+    if response.deleted {
+        if output.is_human() {
+            println!("Deleted tweet id: {}", id);
+        } else {
+            output.print_one(&DeleteRecord {
+                id: id.to_string(),
+                deleted: true,
+                error: None,
+            })?;
+        }
         Ok(())
     } else {
         Err(TwitterError::Api(format!(
             "Error deleting tweet ID: {}",
-            args.id
+            id
         )))
     }
 }
+
+pub fn execute(base_args: &BaseArgs) -> Result<(), TwitterError> {
+    if base_args.is_requesting_help() {
+        return help();
+    }
+    let args = parse(base_args)?;
+    let output = Format::parse(base_args)?;
+    let credentials = credentials::get(base_args)?;
+    base_args.debug(&credentials);
+
+    let mut client = twitter::Client::new(&credentials, base_args);
+    if let Target::One(ref id) = args.target {
+        return delete_one(&client, &output, id);
+    }
+
+    // Deletes are limited to 50 per 15 minutes, so a big cleanup is going to spend most of its time waiting.
+    // Finding them can take a lot of pages too
+    if base_args.get_option::<u32>("retry", "retry").is_none() {
+        client = client.retries(PAGINATION_RETRIES);
+    }
+    let doomed = find(base_args, &client, &args.target)?;
+    if doomed.is_empty() {
+        eprintln!("Nothing to delete");
+        return Ok(());
+    }
+    summarize(&doomed, &credentials, base_args);
+    if !args.yes && !prompt::confirm("Delete them?")? {
+        eprintln!("Not deleting anything");
+        return Ok(());
+    }
+
+    let mut stream = output.stream();
+    let mut failed = Vec::new();
+    for tweet in doomed.iter() {
+        client.wait_for_rate_limit("delete");
+        let record = match client.delete_v2(&tweet.id) {
+            Ok(response) if response.deleted => DeleteRecord {
+                id: tweet.id.to_string(),
+                deleted: true,
+                error: None,
+            },
+            Ok(_) => DeleteRecord {
+                id: tweet.id.to_string(),
+                deleted: false,
+                error: Some(String::from("Twitter said it wasn't deleted")),
+            },
+            Err(err) => DeleteRecord {
+                id: tweet.id.to_string(),
+                deleted: false,
+                error: Some(err.to_string()),
+            },
+        };
+        if output.is_human() {
+            match record.error {
+                None => println!("Deleted tweet id: {}", record.id),
+                Some(ref error) => eprintln!("Could not delete {}: {}", record.id, error),
+            }
+        } else {
+            stream.print(std::slice::from_ref(&record))?;
+        }
+        if !record.deleted {
+            failed.push(record);
+        }
+    }
+    if !output.is_human() {
+        stream.finish();
+    }
+
+    if output.is_human() {
+        println!(
+            "Deleted {} of {} tweets",
+            doomed.len() - failed.len(),
+            doomed.len()
+        );
+    }
+    if failed.is_empty() {
+        return Ok(());
+    }
+    eprintln!("These weren't deleted:");
+    for record in failed.iter() {
+        eprintln!(
+            "    {}: {}",
+            record.id,
+            record.error.as_deref().unwrap_or_default()
+        );
+    }
+    Err(TwitterError::Api(format!(
+        "Could not delete {} of {} tweets",
+        failed.len(),
+        doomed.len()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(args: &[&str]) -> Result<Target, TwitterError> {
+        let args = ["tw", "delete"]
+            .iter()
+            .chain(args)
+            .map(|arg| arg.to_string());
+        parse(&BaseArgs::from_args(args.collect())?).map(|args| args.target)
+    }
+
+    #[test]
+    fn targets() {
+        assert!(matches!(target(&["20"]), Ok(Target::One(id)) if id == "20"));
+        assert!(matches!(
+            target(&["https://twitter.com/jack/status/20?s=1"]),
+            Ok(Target::One(id)) if id == "20"
+        ));
+        assert!(matches!(target(&["last"]), Ok(Target::Last)));
+        assert!(matches!(target(&["--thread", "21"]), Ok(Target::Thread(id)) if id == "21"));
+        assert!(matches!(
+            target(&["--before", "2023-01-01T14:00:00+02:00"]),
+            Ok(Target::Before(date)) if date == "2023-01-01T12:00:00Z"
+        ));
+        assert!(matches!(
+            target(&["--from-file", "ids.txt"]),
+            Ok(Target::FromFile(path)) if path == "ids.txt"
+        ));
+        assert!(matches!(target(&[]), Err(TwitterError::MissingArgument(_))));
+        assert!(matches!(target(&["jack"]), Err(TwitterError::Invalid(_))));
+        assert!(matches!(
+            target(&["20", "--thread", "21"]),
+            Err(TwitterError::Invalid(_))
+        ));
+        match target(&["--before", "2023-01-01T12:00:00é1:00"]) {
+            Err(TwitterError::Invalid(message)) => assert!(message.contains("--before")),
+            _ => panic!("Expected --before to be turned down"),
+        }
+    }
+
+    #[test]
+    fn from_file() {
+        let text = "# spring cleaning\n20\n\n  https://twitter.com/jack/status/21  \n#22\n";
+        let ids = read_ids("ids.txt", text).unwrap();
+        assert_eq!(
+            ids.iter()
+                .map(|doomed| doomed.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["20", "21"]
+        );
+        match read_ids("ids.txt", "20\n\nnot a tweet\n") {
+            Err(TwitterError::Invalid(message)) => {
+                assert!(message.starts_with("Line 3 of ids.txt: not a tweet"))
+            }
+            _ => panic!("Expected line 3 to be turned down"),
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use super::super::args::BaseArgs;
 use super::super::credentials;
 use super::super::error::TwitterError;
+use super::super::output::Format;
 use super::super::twitter;
 
 const HELP: &str = "Read your feed!\n
@@ -11,6 +12,11 @@ Arguments
         integer between 1 and 100.
 
 Options:
+    -o, --output <format>
+        Print json, jsonl or csv instead of the usual human friendly output.
+    --format <template>
+        Print each tweet with a template, e.g. '{author.handle}: {text} ({metrics.likes} likes)',
+        or the name of a template in ~/.twitter_config.toml.
     -p, --profile <name>
         The name of the profile to use.
         Must correspond to an entry in your credentials file (~/.twitter_credentials.toml by default).
@@ -27,6 +33,10 @@ Examples:
         tw feed 20
     Read 1 tweet from your alt feed:
         tw feed 1 -p alt1
+    Get your feed as JSON:
+        tw feed 20 --output json
+    One line per tweet:
+        tw feed --format '{author.handle}: {text}'
 ";
 
 struct Args {
@@ -50,14 +60,20 @@ pub fn execute(base_args: &BaseArgs) -> Result<(), TwitterError> {
     if base_args.is_requesting_help() {
         return help();
     }
-    let args = parse(&base_args);
+    let args = parse(base_args);
+    let output = Format::parse(base_args)?;
     let credentials = credentials::get(base_args)?;
     base_args.debug(&credentials);
 
     let feed = twitter::Client::new(&credentials, base_args).feed(args.count)?;
 
-    for item in feed {
-        item.display();
+    if output.is_human() {
+        for item in feed {
+            item.display();
+        }
+    } else {
+        let records = feed.iter().map(|item| item.record()).collect::<Vec<_>>();
+        output.print(&records)?;
     }
 
     Ok(())
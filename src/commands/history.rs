@@ -0,0 +1,154 @@
+use super::super::args::BaseArgs;
+use super::super::credentials;
+use super::super::error::TwitterError;
+use super::super::history;
+use super::super::output::{Format, HistoryRecord};
+use super::super::twitter::ids;
+
+const HELP: &str = "Look back at what you've posted with tw!\n
+Usage: tw history [list] [count]
+       tw history show <id>
+       tw history search <text>
+
+Every tweet, reply and thread posted with `tw post` is recorded in ~/.twitter_history/<profile>.jsonl
+(or $TW_HISTORY_DIR), unless it was posted with --no-history.
+
+Subcommands:
+    list [count]
+        The most recent tweets, newest first. Defaults to 20.
+    show <id or url>
+        A tweet, along with the rest of the thread it's part of.
+    search <text>
+        Every tweet containing <text>, ignoring case.
+
+Options:
+    --all-profiles
+        Look through the history of every profile instead of just one.
+    -o, --output <format>
+        Print json, jsonl or csv instead of the usual human friendly output.
+    --format <template>
+        Print each tweet with a template, e.g. '{posted_at} {profile} {url}',
+        or the name of a template in ~/.twitter_config.toml.
+    -p, --profile <name>
+        The name of the profile whose history to use.
+    -c, --credentials <name>
+        The file name or path to use for the credentials file.
+        Default: ~/.twitter_credentials.toml
+    --debug
+        Print debug messages.
+
+Examples:
+    What did I just post?
+        tw history
+    Who posted that announcement, and from which account?
+        tw history search \"now available\" --all-profiles
+    The whole thread a tweet belongs to:
+        tw history show 1234567890
+";
+
+fn help() -> Result<(), TwitterError> {
+    println!("{}", HELP);
+    Ok(())
+}
+
+fn display(record: &HistoryRecord) {
+    println!("---------------------------------\n");
+    println!("{}\n", record.text);
+    println!(
+        "{}  @{} [{}]",
+        record.posted_at, record.handle, record.profile
+    );
+    if let Some(ref in_reply_to_id) = record.in_reply_to_id {
+        println!("in reply to: {}", in_reply_to_id);
+    }
+    println!("id: {}", record.id);
+    println!("{}\n", record.url);
+}
+
+fn print(output: &Format, records: &[HistoryRecord]) -> Result<(), TwitterError> {
+    if output.is_human() {
+        if records.is_empty() {
+            println!("Nothing here yet");
+        }
+        for record in records {
+            display(record);
+        }
+        Ok(())
+    } else {
+        output.print(records)
+    }
+}
+
+fn read(base_args: &BaseArgs) -> Result<Vec<HistoryRecord>, TwitterError> {
+    if base_args.flags.contains_key("all-profiles") {
+        history::read_all()
+    } else {
+        history::read(&credentials::profile_name(base_args))
+    }
+}
+
+fn list(base_args: &BaseArgs, output: &Format) -> Result<(), TwitterError> {
+    let count = match base_args.get_position::<String>(2) {
+        Some(count) => count
+            .parse::<usize>()
+            .map_err(|_| TwitterError::Invalid(format!("Count must be a number, not {}", count)))?,
+        None => 20,
+    };
+    let records = read(base_args)?
+        .into_iter()
+        .rev()
+        .take(count)
+        .collect::<Vec<_>>();
+    print(output, &records)
+}
+
+fn show(base_args: &BaseArgs, output: &Format) -> Result<(), TwitterError> {
+    let id = base_args
+        .get_position::<String>(2)
+        .ok_or_else(|| TwitterError::MissingArgument(String::from("id")))?;
+    let id = ids::tweet_id(&id)?;
+    let records = read(base_args)?;
+    let record = records
+        .iter()
+        .find(|record| record.id == id)
+        .ok_or_else(|| {
+            TwitterError::Invalid(format!(
+                "Tweet {} isn't in your history. Try --all-profiles?",
+                id
+            ))
+        })?;
+    let thread = records
+        .iter()
+        .filter(|other| other.thread_root_id == record.thread_root_id)
+        .cloned()
+        .collect::<Vec<_>>();
+    print(output, &thread)
+}
+
+fn search(base_args: &BaseArgs, output: &Format) -> Result<(), TwitterError> {
+    let text = base_args.positional[2..].join(" ");
+    if text.is_empty() {
+        return Err(TwitterError::MissingArgument(String::from("search text")));
+    }
+    print(output, &history::search(read(base_args)?, &text))
+}
+
+pub fn execute(base_args: &BaseArgs) -> Result<(), TwitterError> {
+    if base_args.is_requesting_help() {
+        return help();
+    }
+    let output = Format::parse(base_args)?;
+    let subcommand = base_args
+        .get_position::<String>(1)
+        .unwrap_or_else(|| String::from("list"));
+
+    match subcommand.as_str() {
+        "list" | "ls" => list(base_args, &output),
+        "show" => show(base_args, &output),
+        "search" => search(base_args, &output),
+        _ => {
+            println!("Unknown subcommand: {}", subcommand);
+            help()
+        }
+    }
+}
@@ -1,20 +1,40 @@
 use super::super::args::BaseArgs;
 use super::super::credentials;
 use super::super::error::TwitterError;
-use super::super::twitter;
+use super::super::output::Format;
+use super::super::time;
+use super::super::twitter::{self, ids, TwitterUser};
+use serde_json::json;
 
 const HELP: &str = "See your most recent tweets!\n
 Usage: tw home [count] [OPTIONS]
 
 Arguments
     count (default: 10):
-        integer between 5 and 100.
+        integer between 5 and 100. With --all or --limit it's the page size instead (default: 100).
 
 Options:
+    -u, --user <user>
+        Somebody else's tweets instead of yours: a user id, @handle or a link to their profile.
     -t, --next-token <token>
         A continuation token when paginating results
+    --all
+        Keep fetching pages until there are no more tweets. Twitter only goes back 3200 tweets.
+    --limit <n>
+        Keep fetching pages until <n> tweets have been printed. When that's halfway through a page, the token
+        printed at the end fetches that page again, so nothing gets skipped when you carry on.
+    --since <date>
+        Only tweets from <date> onwards, e.g. 2023-01-31 or 2023-01-31T12:00:00Z.
+        Implies --all unless --limit is given.
+    --retry <n>
+        Retry rate limited and failed requests up to <n> times. Defaults to 3 when paginating.
     --dump
-        Write raw JSON result to a file
+        Write raw JSON result to a file. When paginating, all pages go into one file.
+    -o, --output <format>
+        Print json, jsonl or csv instead of the usual human friendly output.
+    --format <template>
+        Print each tweet with a template, e.g. '{author.handle}: {text} ({metrics.likes} likes)',
+        or the name of a template in ~/.twitter_config.toml.
     -p, --profile <name>
         The name of the profile to use.
         Must correspond to an entry in your credentials file (~/.twitter_credentials.toml by default).
@@ -31,20 +51,78 @@ Examples:
         tw home 20
     Read your last 5 tweets from your alt profile:
         tw home 5 -p alt1
+    Read your last 500 tweets:
+        tw home --limit 500
+    See what somebody else has been up to:
+        tw home --user @jack
+    Save everything you tweeted this year to a single file:
+        tw home --since 2023-01-01 --dump
 ";
 
+// Pages can fail halfway through a long run, so ride out rate limits unless told otherwise
+pub const PAGINATION_RETRIES: u32 = 3;
+
 struct Args {
     count: i32,
-    next_token: Option<String>
+    next_token: Option<String>,
+    paginate: bool,
+    limit: Option<usize>,
+    since: Option<String>,
+    user: Option<String>,
 }
 
-fn parse(args: &BaseArgs) -> Args {
+fn parse(args: &BaseArgs) -> Result<Args, TwitterError> {
+    let limit = match args.get_option::<String>("limit", "limit") {
+        Some(limit) => match limit.parse::<usize>() {
+            Ok(limit) if limit > 0 => Some(limit),
+            _ => {
+                return Err(TwitterError::Invalid(format!(
+                    "--limit must be a positive number, not {}",
+                    limit
+                )))
+            }
+        },
+        None => None,
+    };
+    let since = match args.get_option::<String>("since", "since") {
+        Some(since) => Some(start_time("--since", &since)?),
+        None => None,
+    };
+    let paginate = args.get_flag("all", "all") || limit.is_some() || since.is_some();
     let count = match args.get_position::<String>(1) {
         Some(count) => count.parse::<i32>().unwrap(),
+        None if paginate => 100,
         None => 10,
     };
     let next_token = args.get_option("next-token", "t");
-    Args { count, next_token }
+    Ok(Args {
+        count,
+        next_token,
+        paginate,
+        limit,
+        since,
+        user: args.get_option("user", "u"),
+    })
+}
+
+// The API wants RFC 3339 in UTC, but a plain date is what you'd actually type. `flag` is for the error message
+pub fn start_time(flag: &str, since: &str) -> Result<String, TwitterError> {
+    let is_date = |date: &str| {
+        date.len() == 10
+            && date.chars().enumerate().all(|(i, c)| match i {
+                4 | 7 => c == '-',
+                _ => c.is_ascii_digit(),
+            })
+    };
+    if is_date(since) {
+        return Ok(format!("{}T00:00:00Z", since));
+    }
+    time::to_utc(since).ok_or_else(|| {
+        TwitterError::Invalid(format!(
+            "{} should look like 2023-01-31, 2023-01-31T12:00:00Z or 2023-01-31T14:00:00+02:00, not {}",
+            flag, since
+        ))
+    })
 }
 
 fn help() -> Result<(), TwitterError> {
@@ -56,7 +134,8 @@ pub fn execute(base_args: &BaseArgs) -> Result<(), TwitterError> {
     if base_args.is_requesting_help() {
         return help();
     }
-    let args = parse(&base_args);
+    let args = parse(base_args)?;
+    let output = Format::parse(base_args)?;
     if args.count < 5 || args.count > 100 {
         return Err(TwitterError::Invalid(
             "Count must be between 5 and 100".to_string(),
@@ -65,20 +144,128 @@ pub fn execute(base_args: &BaseArgs) -> Result<(), TwitterError> {
     let credentials = credentials::get(base_args)?;
     base_args.debug(&credentials);
 
-    let me = twitter::Client::new(&credentials, base_args).me()?;
-    let home = twitter::Client::new(&credentials, base_args).home_v2(&me.id, args.count, args.next_token)?;
+    let mut client = twitter::Client::new(&credentials, base_args);
+    if args.paginate {
+        // One combined dump instead of a file per page
+        client = client.dump_responses(false);
+        if base_args.get_option::<u32>("retry", "retry").is_none() {
+            client = client.retries(PAGINATION_RETRIES);
+        }
+    }
+    let user = match args.user {
+        Some(ref user) => ids::lookup_user(&client, user)?,
+        None => client.me()?,
+    };
+    if args.paginate {
+        return paginate(base_args, &client, &args, &output, &user);
+    }
+    let home = client.home_v2(&user.id, args.count, args.next_token, None)?;
 
-    for item in home.data {
-        item.display();
+    if output.is_human() {
+        for item in home.data.iter() {
+            item.display();
+        }
+    } else {
+        let records = home
+            .data
+            .iter()
+            .map(|item| item.record(Some(&user)))
+            .collect::<Vec<_>>();
+        output.print(&records)?;
     }
 
-    match home.meta {
-        Some(meta) => match meta.next_token {
-            Some(token) => println!("Next page token: {}", token),
-            None => ()
-        },
-        None => ()
+    if let Some(meta) = home.meta {
+        if let Some(token) = meta.next_token {
+            print_next_token(&output, &token);
+        }
     }
 
     Ok(())
 }
+
+fn print_next_token(output: &Format, token: &str) {
+    // Keep stdout clean for whatever is parsing it
+    if output.is_human() {
+        println!("Next page token: {}", token)
+    } else {
+        eprintln!("Next page token: {}", token)
+    }
+}
+
+// Prints every page as soon as it arrives, so a long run shows progress (and a failed one keeps what it got)
+fn paginate(
+    base_args: &BaseArgs,
+    client: &twitter::Client,
+    args: &Args,
+    output: &Format,
+    user: &TwitterUser,
+) -> Result<(), TwitterError> {
+    let dump = base_args.flags.contains_key("dump");
+    let mut pages = client.home_pages(
+        &user.id,
+        args.count,
+        args.since.clone(),
+        args.next_token.clone(),
+    );
+
+    let mut stream = output.stream();
+    let mut dumped = Vec::new();
+    let mut printed = 0;
+    let mut cut_short = false;
+    let mut result = Ok(());
+    for page in pages.by_ref() {
+        let mut page = match page {
+            Ok(page) => page,
+            Err(err) => {
+                result = Err(err);
+                break;
+            }
+        };
+        if let Some(limit) = args.limit {
+            cut_short = page.len() > limit - printed;
+            page.truncate(limit - printed);
+        }
+        printed += page.len();
+
+        if output.is_human() {
+            for item in page.iter() {
+                item.display();
+            }
+        } else {
+            let records = page
+                .iter()
+                .map(|item| item.record(Some(user)))
+                .collect::<Vec<_>>();
+            if let Err(err) = stream.print(&records) {
+                result = Err(err);
+                break;
+            }
+        }
+        if dump {
+            dumped.append(&mut page);
+        }
+        if args.limit == Some(printed) {
+            break;
+        }
+    }
+    if !output.is_human() {
+        stream.finish();
+    }
+
+    if dump {
+        client.dump(
+            "home",
+            &serde_json::to_string_pretty(&json!({ "data": dumped }))?,
+        )?;
+    }
+    // Stopped early (limit or error), so say where to pick up again. When --limit stopped halfway through a page,
+    // that's the start of the page: a few tweets show up twice instead of the rest of it never showing up
+    let token = match cut_short {
+        true => pages.page_token(),
+        false => pages.next_token(),
+    };
+    if let Some(token) = token {
+        print_next_token(output, token);
+    }
+    result
+}
@@ -1,6 +1,7 @@
 use super::super::args::BaseArgs;
-use super::super::credentials::{Credentials, CredentialsFile};
+use super::super::credentials::{self, Credentials, CredentialsFile};
 use super::super::error::TwitterError;
+use super::super::private;
 use std::fs;
 use std::path::PathBuf;
 
@@ -8,6 +9,11 @@ const HELP: &str = "Initialize your credentials file!\n
 Usage: tw init [OPTIONS]
 
 Options:
+    --login
+        Log in with your Twitter account instead of filling in the access token by hand.
+        Same as `tw login`.
+    -p, --profile <name>
+        The name of the profile to log in to with --login.
     -c, --credentials <name>
         The file name or path to use for the credentials file.
         Default: ~/.twitter_credentials.toml
@@ -17,31 +23,15 @@ Examples:
         tw init
     Initialize with custom file path:
         tw init -c /path/to/credentials.toml
+    Initialize and log in:
+        tw init --login
 ";
 
-struct Args {
-    credentials_file: String,
-}
-
-fn parse(args: &BaseArgs) -> Args {
-    let credentials_file = args.get(
-        "credentials",
-        "c",
-        String::from(".twitter_credentials.toml"),
-    );
-    Args { credentials_file }
-}
-
 fn help() -> Result<(), TwitterError> {
     println!("{}", HELP);
     Ok(())
 }
 
-fn home_dir() -> PathBuf {
-    home::home_dir()
-        .expect("Cannot get your home directory! Please pass the path to your .twitter_credentials.toml manually using -c or --credentials")
-}
-
 fn write_empty_credentials(path: &PathBuf) -> Result<(), TwitterError> {
     let credentials = Credentials {
         api_key: "".to_string(),
@@ -49,12 +39,13 @@ fn write_empty_credentials(path: &PathBuf) -> Result<(), TwitterError> {
         access_token: "".to_string(),
         access_token_secret: "".to_string(),
         handle: "".to_string(),
+        ..Default::default()
     };
     let credentials_file = CredentialsFile {
         default: credentials,
     };
     let contents = toml::to_string(&credentials_file)?;
-    match fs::write(path, contents) {
+    match private::write(path, contents) {
         Ok(_) => {
             println!(
               "✅ Credentials file succesfully initialized. Please open {:?} and fill in the values",
@@ -74,13 +65,14 @@ pub fn execute(base_args: &BaseArgs) -> Result<(), TwitterError> {
     if base_args.is_requesting_help() {
         return help();
     }
-    let args = parse(&base_args);
-    let mut path = PathBuf::from(home_dir());
-    path.push(args.credentials_file);
+    if base_args.get_flag("login", "l") {
+        return super::login(base_args);
+    }
+    let path = credentials::path(base_args);
 
     match fs::canonicalize(&path) {
         Ok(_) => match fs::read_to_string(&path) {
-            Ok(contents) if contents != "" => {
+            Ok(contents) if !contents.is_empty() => {
                 println!(
                     "🤨 Credentials file {:?} already exists and is non-empty!",
                     &path
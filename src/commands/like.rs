@@ -1,13 +1,18 @@
 use super::super::args::BaseArgs;
 use super::super::credentials;
 use super::super::error::TwitterError;
-use super::super::twitter;
+use super::super::output::{Format, LikeRecord};
+use super::super::twitter::{self, ids};
 
 const HELP: &str = "Like (or unlike) a tweet!\n
-Usage: tw like tweet_id [OPTIONS]
-       tw unlike tweet_id [OPTIONS]
+Usage: tw like <id or url> [OPTIONS]
+       tw unlike <id or url> [OPTIONS]
 
 Options:
+    -o, --output <format>
+        Print json, jsonl or csv instead of the usual human friendly output.
+    --format <template>
+        Print the result with a template, e.g. '{id} {liked}', or the name of a template in ~/.twitter_config.toml.
     -p, --profile <name>
         The name of the profile to use.
         Must correspond to an entry in your credentials file (~/.twitter_credentials.toml by default).
@@ -32,7 +37,9 @@ struct Args {
 
 fn parse(args: &BaseArgs) -> Result<Args, TwitterError> {
     match args.get_position::<String>(1) {
-        Some(id) if id != "" => Ok(Args { id }),
+        Some(id) if !id.is_empty() => Ok(Args {
+            id: ids::tweet_id(&id)?,
+        }),
         _ => Err(TwitterError::MissingArgument("id".to_string())),
     }
 }
@@ -51,18 +58,23 @@ fn execute(base_args: &BaseArgs, like_or_unlike: Action) -> Result<(), TwitterEr
     if base_args.is_requesting_help() {
         return help();
     }
-    let args = parse(&base_args)?;
+    let args = parse(base_args)?;
+    let output = Format::parse(base_args)?;
     let credentials = credentials::get(base_args)?;
     base_args.debug(&credentials);
 
-    let me = twitter::Client::new(&credentials, base_args).me()?;
+    let client = twitter::Client::new(&credentials, base_args);
+    let me = client.me()?;
     let response = match like_or_unlike {
-        Action::Like => twitter::Client::new(&credentials, base_args).like_v2(&me.id, &args.id)?,
-        Action::Unlike => {
-            twitter::Client::new(&credentials, base_args).unlike_v2(&me.id, &args.id)?
-        }
+        Action::Like => client.like_v2(&me.id, &args.id)?,
+        Action::Unlike => client.unlike_v2(&me.id, &args.id)?,
     };
-    if response.liked {
+    if !output.is_human() {
+        output.print_one(&LikeRecord {
+            id: args.id,
+            liked: response.liked,
+        })?;
+    } else if response.liked {
         println!("Liked tweet id: {}", args.id);
     } else {
         println!("Unliked tweet id: {}", args.id);
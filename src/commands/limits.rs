@@ -0,0 +1,77 @@
+use super::super::args::BaseArgs;
+use super::super::credentials;
+use super::super::error::TwitterError;
+use super::super::output::Format;
+use super::super::twitter;
+
+const HELP: &str = "Check how close you are to getting rate limited!\n
+Usage: tw limits [...resources] [OPTIONS]
+
+By default only endpoints you have actually used in the current window are shown.
+
+Arguments
+    resources (optional):
+        Resource families to check, e.g. statuses, users, application.
+
+Options:
+    --all
+        Show every endpoint, including the ones you haven't touched.
+    -o, --output <format>
+        Print json, jsonl or csv instead of the usual human friendly output.
+    --format <template>
+        Print each endpoint with a template, e.g. '{endpoint} {remaining}/{limit}',
+        or the name of a template in ~/.twitter_config.toml.
+    -p, --profile <name>
+        The name of the profile to use.
+        Must correspond to an entry in your credentials file (~/.twitter_credentials.toml by default).
+    -c, --credentials <name>
+        The file name or path to use for the credentials file.
+        Default: ~/.twitter_credentials.toml
+    --debug
+        Print debug messages.
+
+Examples:
+    Check the limits you've been chewing through:
+        tw limits
+    Check every statuses endpoint:
+        tw limits statuses --all
+";
+
+struct Args {
+    resources: Vec<String>,
+    show_all: bool,
+}
+
+fn parse(args: &BaseArgs) -> Args {
+    let resources = args.positional[1..].to_vec();
+    let show_all = args.get_flag("all", "a");
+    Args {
+        resources,
+        show_all,
+    }
+}
+
+fn help() -> Result<(), TwitterError> {
+    println!("{}", HELP);
+    Ok(())
+}
+
+pub fn execute(base_args: &BaseArgs) -> Result<(), TwitterError> {
+    if base_args.is_requesting_help() {
+        return help();
+    }
+    let args = parse(base_args);
+    let output = Format::parse(base_args)?;
+    let credentials = credentials::get(base_args)?;
+    base_args.debug(&credentials);
+
+    let status =
+        twitter::Client::new(&credentials, base_args).rate_limit_status(&args.resources)?;
+    if output.is_human() {
+        status.display(args.show_all);
+    } else {
+        output.print(&status.records(args.show_all))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,175 @@
+use super::super::args::BaseArgs;
+use super::super::credentials::{self, Credentials};
+use super::super::error::TwitterError;
+use super::super::prompt;
+use super::super::twitter::{self, oauth2};
+use toml::Value;
+
+const HELP: &str = "Log in with your Twitter account!\n
+Usage: tw login [OPTIONS]
+       tw init --login [OPTIONS]
+
+Uses PIN-based OAuth to fetch an access token and secret for your account, then saves them to your credentials file.
+You only need your app's API key and secret; if the profile doesn't have them yet you will be asked for them.
+
+With --oauth2, logs in with OAuth 2.0 (Authorization Code with PKCE) instead. Some endpoints, like bookmarks, only
+work with OAuth 2.0. You'll need your app's OAuth 2.0 client ID (and secret, for confidential clients), and the app's
+callback URL must be set to http://127.0.0.1:<port>/callback in the developer portal.
+
+Options:
+    --oauth2
+        Log in with OAuth 2.0 instead of OAuth 1.0a.
+    --scopes <scopes>
+        Space separated OAuth 2.0 scopes to ask for.
+        Default: tweet.read tweet.write users.read like.read like.write bookmark.read offline.access
+    --port <port>
+        Port to listen on for the OAuth 2.0 callback.
+        Default: 8739
+    -p, --profile <name>
+        The name of the profile to log in to. It will be created if it doesn't exist.
+        Default: default
+    -c, --credentials <name>
+        The file name or path to use for the credentials file.
+        Default: ~/.twitter_credentials.toml
+    --debug
+        Print debug messages.
+
+Examples:
+    Log in to your default profile:
+        tw login
+    Log in to your alt account:
+        tw login -p alt1
+    Log in with OAuth 2.0:
+        tw login --oauth2
+";
+
+fn help() -> Result<(), TwitterError> {
+    println!("{}", HELP);
+    Ok(())
+}
+
+pub fn execute(base_args: &BaseArgs) -> Result<(), TwitterError> {
+    if base_args.is_requesting_help() {
+        return help();
+    }
+    if base_args.get_flag("oauth2", "oauth2") {
+        return login_oauth2(base_args);
+    }
+    let path = credentials::path(base_args);
+    let profile = credentials::profile_name(base_args);
+
+    // Reuse whatever the profile already has, so re-logging in only needs the PIN
+    let table = credentials::read_table(&path)?;
+    let existing = |key: &str| {
+        table
+            .get(&profile)
+            .and_then(|profile| profile.get(key))
+            .and_then(Value::as_str)
+            .filter(|value| !value.is_empty())
+            .map(String::from)
+    };
+    let api_key = match existing("api_key") {
+        Some(api_key) => api_key,
+        None => prompt::ask_required("API key:")?,
+    };
+    let api_key_secret = match existing("api_key_secret") {
+        Some(api_key_secret) => api_key_secret,
+        None => prompt::ask_required("API key secret:")?,
+    };
+    let mut credentials = Credentials {
+        api_key,
+        api_key_secret,
+        access_token: String::new(),
+        access_token_secret: String::new(),
+        handle: String::new(),
+        api_base: existing("api_base"),
+        ..Default::default()
+    };
+
+    let access_token = {
+        let client = twitter::Client::new(&credentials, base_args);
+        let request_token = client.request_token()?;
+        println!(
+            "Open this URL in your browser, authorize the app, and copy the PIN:\n\n    {}\n",
+            client.authorize_url(&request_token)
+        );
+        let pin = prompt::ask_required("PIN:")?;
+        client.access_token(&request_token, &pin)?
+    };
+
+    credentials.access_token = access_token.oauth_token;
+    credentials.access_token_secret = access_token.oauth_token_secret;
+    credentials.handle = access_token.screen_name.unwrap_or_default();
+    credentials::write_profile(&path, &profile, &credentials)?;
+
+    println!(
+        "✅ Logged in as @{}. Saved to profile [{}] in {:?}",
+        credentials.handle, profile, &path
+    );
+    Ok(())
+}
+
+fn login_oauth2(base_args: &BaseArgs) -> Result<(), TwitterError> {
+    let path = credentials::path(base_args);
+    let profile = credentials::profile_name(base_args);
+    let scopes = base_args.get("scopes", "scopes", oauth2::DEFAULT_SCOPES.to_string());
+    let port = base_args.get("port", "port", oauth2::DEFAULT_PORT);
+
+    let table = credentials::read_table(&path)?;
+    let existing = |key: &str| {
+        table
+            .get(&profile)
+            .and_then(|profile| profile.get(key))
+            .and_then(Value::as_str)
+            .filter(|value| !value.is_empty())
+            .map(String::from)
+    };
+    let client_id = match existing("client_id") {
+        Some(client_id) => client_id,
+        None => prompt::ask_required("OAuth 2.0 client ID:")?,
+    };
+    let client_secret = match existing("client_secret") {
+        Some(client_secret) => Some(client_secret),
+        None => Some(prompt::ask(
+            "OAuth 2.0 client secret (leave blank for public clients):",
+        )?)
+        .filter(|secret| !secret.is_empty()),
+    };
+    let credentials = Credentials {
+        api_base: existing("api_base"),
+        client_id: Some(client_id),
+        client_secret,
+        ..Default::default()
+    };
+
+    let client = twitter::Client::new(&credentials, base_args);
+    let pkce = oauth2::Pkce::new();
+    let redirect_uri = oauth2::redirect_uri(port);
+    let listener = oauth2::listen(port)?;
+    println!(
+        "Open this URL in your browser and authorize the app:\n\n    {}\n",
+        client.oauth2_authorize_url(&pkce, &redirect_uri, &scopes)
+    );
+    println!(
+        "Waiting for Twitter to send you back to {} ...",
+        redirect_uri
+    );
+    let code = oauth2::wait_for_code(&listener, &pkce.state)?;
+    let token = client.oauth2_exchange_code(&code, &pkce, &redirect_uri)?;
+
+    let set =
+        |key: &str, value: Value| credentials::write_profile_value(&path, &profile, key, value);
+    set("client_id", Value::try_from(&credentials.client_id)?)?;
+    if let Some(ref client_secret) = credentials.client_secret {
+        set("client_secret", Value::try_from(client_secret)?)?;
+    }
+    set("oauth2", Value::try_from(&token)?)?;
+
+    println!(
+        "✅ Logged in with OAuth 2.0 (scopes: {}). Saved to profile [{}] in {:?}",
+        token.scopes.join(" "),
+        profile,
+        &path
+    );
+    Ok(())
+}
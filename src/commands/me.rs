@@ -1,12 +1,19 @@
 use super::super::args::BaseArgs;
 use super::super::credentials;
 use super::super::error::TwitterError;
+use super::super::output::Format;
 use super::super::twitter;
 
 const HELP: &str = "Get some details about yourself!\n
 Usage: tw me [OPTIONS]
 
 Options:
+    --refresh
+        Fetch your details from Twitter again instead of using the cached copy in ~/.twitter_cache.toml.
+    -o, --output <format>
+        Print json, jsonl or csv instead of the usual human friendly output.
+    --format <template>
+        Print the result with a template, e.g. '{id} {url}', or the name of a template in ~/.twitter_config.toml.
     -p, --profile <name>
         The name of the profile to use.
         Must correspond to an entry in your credentials file (~/.twitter_credentials.toml by default).
@@ -21,6 +28,8 @@ Examples:
         tw me
     Get user summary from your alt home:
         tw me -p alt1
+    Pick up a new handle or name:
+        tw me --refresh
 ";
 
 fn help() -> Result<(), TwitterError> {
@@ -32,12 +41,21 @@ pub fn execute(base_args: &BaseArgs) -> Result<(), TwitterError> {
     if base_args.is_requesting_help() {
         return help();
     }
+    let output = Format::parse(base_args)?;
     let credentials = credentials::get(base_args)?;
     base_args.debug(&credentials);
 
-    let me = twitter::Client::new(&credentials, base_args).me()?;
+    let client = twitter::Client::new(&credentials, base_args);
+    let me = match base_args.get_flag("refresh", "refresh") {
+        true => client.refresh_me()?,
+        false => client.me()?,
+    };
 
-    me.display();
+    if output.is_human() {
+        me.display();
+    } else {
+        output.print_one(&me.record())?;
+    }
 
     Ok(())
 }
@@ -1,5 +1,6 @@
 pub mod post;
-pub use post::execute as post;
+pub use post::execute_post as post;
+pub use post::execute_quote as quote;
 
 pub mod delete;
 pub use delete::execute as delete;
@@ -19,3 +20,31 @@ pub use me::execute as me;
 pub mod like;
 pub use like::execute_like as like;
 pub use like::execute_unlike as unlike;
+
+pub mod retweet;
+pub use retweet::execute_retweet as retweet;
+pub use retweet::execute_unretweet as unretweet;
+
+pub mod limits;
+pub use limits::execute as limits;
+
+pub mod login;
+pub use login::execute as login;
+
+pub mod bookmarks;
+pub use bookmarks::execute as bookmarks;
+
+pub mod profile;
+pub use profile::execute as profile;
+
+pub mod auth;
+pub use auth::execute as auth;
+
+pub mod stats;
+pub use stats::execute as stats;
+
+pub mod history;
+pub use history::execute as history;
+
+pub mod count;
+pub use count::execute as count;
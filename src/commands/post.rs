@@ -1,16 +1,69 @@
 use super::super::args::BaseArgs;
-use super::super::credentials;
+use super::super::credentials::{self, Credentials};
+use super::super::editor;
 use super::super::error::TwitterError;
-use super::super::twitter;
+use super::super::history;
+use super::super::output::{Format, HistoryRecord, PostRecord};
+use super::super::prompt;
+use super::super::time;
+use super::super::twitter::{
+    self, ids, media, text, Media, NewTweet, Poll, ReplySettings, TwitterCreateResponseData,
+};
+use std::fs;
+use std::io::{self, Read};
 
 const HELP: &str = "Post a tweet!\n
 Usage: tw post message [...replies] [OPTIONS]
+       tw post --file <path> [OPTIONS]
+       tw post - [OPTIONS]
+       tw post --edit [OPTIONS]
+       tw quote <id or url> message [...replies] [OPTIONS]
 
-Including replies will post a thread
+Including replies will post a thread. `tw quote` is the same as `tw post --quote`, and takes all the same options.
 
 Options:
-    -r, --reply-id <id>
-        The ID of the tweet to reply to.
+    -r, --reply-id <id or url>
+        The tweet to reply to.
+    -f, --file <path>
+        Read the tweet from a file instead, or `-` instead of --file to read it from stdin.
+        Separate the tweets of a thread with a line containing just ---
+    -m, --media [n:]<path>
+        Attach a picture, GIF or video: up to 4 images, or one GIF or video, per tweet. Can be given more than once.
+        In a thread, prefix the path with the number of the tweet it goes on, e.g. --media 2:chart.png
+    --alt <text>
+        Alt text describing an image for people who can't see it. The first --alt goes with the first --media,
+        the second with the second and so on. Use --alt \"\" to skip one.
+    --poll <options>
+        Add a poll to the (first) tweet, with 2 to 4 options separated by commas, e.g. --poll \"Cats,Dogs\".
+        Options can be up to 25 characters. A poll can't go with --media or --quote.
+    --poll-minutes <n>
+        How long the poll runs, from 5 minutes to 7 days (10080). Default: 1440 (a day)
+    -q, --quote <id or url>
+        Quote a tweet. Can't go with --media on the same tweet.
+    --who-can-reply <everyone|mentioned|following>
+        Who can reply: everyone (the default), only the people mentioned, or only people you follow.
+    --super-followers-only
+        Only your Super Followers get to see it.
+    --place <place_id>
+        Tag the (first) tweet with a place, see https://developer.twitter.com/en/docs/twitter-api/v1/geo
+    -e, --edit
+        Write the tweet in $VISUAL or $EDITOR, then look it over before it's posted.
+        Anything passed as arguments or with --file is where the draft starts. Saving an empty file cancels.
+    --delimiter <line>
+        Use something other than --- to separate tweets in --file or --edit.
+    --split
+        Break tweets that are too long into a thread, at the end of a sentence or word where possible.
+    --number
+        Add 1/n, 2/n, ... to the end of every tweet of a thread.
+    --retry <n>
+        Wait out rate limits up to n times. Server errors aren't retried, the tweet may have been posted anyway.
+        Default: 0 for single tweets, 3 for threads.
+    -o, --output <format>
+        Print json, jsonl or csv instead of the usual human friendly output. Threads get one record per tweet.
+    --format <template>
+        Print the result with a template, e.g. '{id} {url}', or the name of a template in ~/.twitter_config.toml.
+    --no-history
+        Don't record the tweets in your local history (~/.twitter_history/<profile>.jsonl), see `tw history`.
     -p, --profile <name>
         The name of the profile to use.
         Must correspond to an entry in your credentials file (~/.twitter_credentials.toml by default).
@@ -29,89 +82,473 @@ Examples:
         tw post \"I took out my wool sweater today and it made me want to THREAD\" \"#sweaterweather\" \"#unnecessarythreading\"
     Post with an alt account:
         tw post \"Hey y'all @ericydauenhauer is real\" --profile alt1
+    Post a thread you wrote in a file:
+        tw post --file thread.md
+    Post a long rant as a numbered thread:
+        pbpaste | tw post - --split --number
+    Post a picture, with a description:
+        tw post \"New office!\" --media office.jpg --alt \"A desk by a window overlooking the harbour\"
+    Post a thread with a chart on the second tweet:
+        tw post \"Q3 numbers are in\" \"Revenue is up\" --media 2:revenue.png
+    Ask the important questions:
+        tw post \"Tabs or spaces?\" --poll \"Tabs,Spaces,Both somehow\" --poll-minutes 60
+    Quote a tweet, and only let the people you follow reply:
+        tw quote https://twitter.com/jack/status/20 \"This 👇\" --who-can-reply following
+    Write it in your editor:
+        tw post --edit
 ";
 
+const THREAD_RETRIES: u32 = 3;
+
 struct Args {
     messages: Vec<String>,
     in_reply_to_tweet_id: Option<String>,
+    history: bool,
+    edit: bool,
+    media: Vec<Media>,
+    quote_tweet_id: Option<String>,
+    poll: Option<Poll>,
+    reply_settings: Option<ReplySettings>,
+    super_followers_only: bool,
+    place_id: Option<String>,
+}
+
+// Tweets in a file are separated by a line with just the delimiter on it
+fn segments(text: &str, delimiter: &str) -> Vec<String> {
+    let mut segments = vec![String::new()];
+    for line in text.lines() {
+        if line.trim() == delimiter {
+            segments.push(String::new());
+        } else if let Some(segment) = segments.last_mut() {
+            segment.push_str(line);
+            segment.push('\n');
+        }
+    }
+    segments
+        .iter()
+        .map(|segment| segment.trim().to_string())
+        .filter(|segment| !segment.is_empty())
+        .collect()
+}
+
+enum Action {
+    Post,
+    Quote,
+}
+
+impl Action {
+    // `tw quote` has the tweet being quoted before the message
+    fn first_message(&self) -> usize {
+        match self {
+            Action::Post => 1,
+            Action::Quote => 2,
+        }
+    }
+}
+
+// The tweets as they were written, from the arguments, --file or stdin
+fn drafts(args: &BaseArgs, action: &Action) -> Result<Vec<String>, TwitterError> {
+    let positional = args
+        .positional
+        .get(action.first_message()..)
+        .unwrap_or_default();
+    let text = match (args.get_option::<String>("file", "f"), positional) {
+        (Some(_), [_, ..]) => {
+            return Err(TwitterError::Invalid(
+                "Pass the message as arguments or with --file, not both".to_string(),
+            ))
+        }
+        (Some(path), []) => Some(fs::read_to_string(path)?),
+        (None, [dash]) if dash == "-" => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text)?;
+            Some(text)
+        }
+        _ => None,
+    };
+    Ok(match text {
+        Some(text) => segments(&text, &delimiter(args)),
+        None => positional.to_vec(),
+    })
+}
+
+fn delimiter(args: &BaseArgs) -> String {
+    args.get("delimiter", "delimiter", String::from("---"))
 }
 
-fn parse(args: &BaseArgs) -> Result<Args, TwitterError> {
-    let messages = args.positional[1..].to_vec();
-    if messages.len() == 0 {
+// Split and numbered, if asked to
+fn arrange(messages: Vec<String>, args: &BaseArgs) -> Vec<String> {
+    let number = args.get_flag("number", "number");
+    if args.get_flag("split", "split") {
+        text::split_thread(&messages, text::MAX_LENGTH, number)
+    } else if number && messages.len() > 1 {
+        text::number(messages)
+    } else {
+        messages
+    }
+}
+
+// The tweets to post, exactly as they'd be posted
+fn tweets(args: &BaseArgs, action: &Action) -> Result<Vec<String>, TwitterError> {
+    let messages = arrange(drafts(args, action)?, args);
+    if messages.is_empty() {
         return Err(TwitterError::MissingArgument("message".to_string()));
     }
-    let in_reply_to_tweet_id = args.get_option("reply-id", "r");
+    Ok(messages)
+}
+
+// Shared with `tw count`, so drafts are counted the same way they're posted
+pub fn messages(args: &BaseArgs) -> Result<Vec<String>, TwitterError> {
+    tweets(args, &Action::Post)
+}
+
+fn parse(args: &BaseArgs, action: &Action) -> Result<Args, TwitterError> {
+    let quote = match action {
+        Action::Post => args.get_option::<String>("quote", "q"),
+        Action::Quote => Some(
+            args.get_position::<String>(1)
+                .ok_or_else(|| TwitterError::MissingArgument("id".to_string()))?,
+        ),
+    };
+    let quote_tweet_id = match quote {
+        Some(quote) => Some(ids::tweet_id(&quote)?),
+        None => None,
+    };
+    // With --edit, whatever was passed is only the start of the draft, and gets checked once it's done
+    let edit = args.get_flag("edit", "e");
+    let messages = match edit {
+        true => drafts(args, action)?,
+        false => {
+            let messages = tweets(args, action)?;
+            text::validate(&messages)?;
+            messages
+        }
+    };
+    let in_reply_to_tweet_id = match args.get_option::<String>("reply-id", "r") {
+        Some(id) => Some(ids::tweet_id(&id)?),
+        None => None,
+    };
+    let history = !args.get_flag("no-history", "no-history");
+    let media = attachments(args)?;
+    let poll_minutes = args.get_option::<String>("poll-minutes", "poll-minutes");
+    let poll = match (args.get_option::<String>("poll", "poll"), poll_minutes) {
+        (Some(options), minutes) => {
+            let minutes = match minutes {
+                Some(minutes) => minutes.parse::<u32>().map_err(|_| {
+                    TwitterError::Invalid(format!(
+                        "--poll-minutes must be a number, not {}",
+                        minutes
+                    ))
+                })?,
+                None => 24 * 60,
+            };
+            Some(Poll::new(&options, minutes)?)
+        }
+        (None, Some(_)) => {
+            return Err(TwitterError::Invalid(
+                "--poll-minutes is for polls, add a --poll".to_string(),
+            ))
+        }
+        (None, None) => None,
+    };
+    // Twitter takes one kind of attachment per tweet
+    if poll.is_some() && media.iter().any(|attachment| attachment.tweet == 0) {
+        return Err(TwitterError::Invalid(
+            "A tweet can have a poll or --media, not both".to_string(),
+        ));
+    }
+    if poll.is_some() && quote_tweet_id.is_some() {
+        return Err(TwitterError::Invalid(
+            "A tweet can have a poll or --quote, not both".to_string(),
+        ));
+    }
+    if quote_tweet_id.is_some() && media.iter().any(|attachment| attachment.tweet == 0) {
+        return Err(TwitterError::Invalid(
+            "A tweet can quote another one or have --media, not both. Put the media on a later tweet, e.g. --media 2:photo.png".to_string(),
+        ));
+    }
+    let reply_settings = match args.get_option::<String>("who-can-reply", "who-can-reply") {
+        Some(value) => ReplySettings::parse(&value)?,
+        None => None,
+    };
     Ok(Args {
         messages,
         in_reply_to_tweet_id,
+        history,
+        edit,
+        media,
+        quote_tweet_id,
+        poll,
+        reply_settings,
+        super_followers_only: args.get_flag("super-followers-only", "super-followers-only"),
+        place_id: args.get_option("place", "place"),
     })
 }
 
-fn help() -> Result<(), TwitterError> {
-    println!("{}", HELP);
-    Ok(())
+// --media and --alt, paired up in the order they were given
+fn attachments(args: &BaseArgs) -> Result<Vec<Media>, TwitterError> {
+    let specs = args.get_all("media", "m");
+    let alt_texts = args.get_all("alt", "alt");
+    if alt_texts.len() > specs.len() {
+        return Err(TwitterError::Invalid(format!(
+            "{} --alt but only {} --media, which picture is each one for?",
+            alt_texts.len(),
+            specs.len()
+        )));
+    }
+    specs
+        .iter()
+        .enumerate()
+        .map(|(i, spec)| {
+            let (tweet, path) = media::parse_spec(spec);
+            let alt_text = alt_texts.get(i).filter(|alt| !alt.is_empty()).cloned();
+            Media::open(path, tweet, alt_text)
+        })
+        .collect()
 }
 
-/*
-Future plans: Append tweets to a file.
-
-use super::super::twitter::TwitterCreateResponseData;
-struct TwitterHistoryFile {
-    tweets: Vec<TwitterCreateResponseData>,
+// Only "# " and a lone "#" make a comment, so a line starting with a #hashtag is still part of the tweet
+fn is_comment(line: &str) -> bool {
+    line == "#" || line.starts_with("# ")
 }
 
-// Not a good look to have this copoy/pasted from credentials.rs ¯\_(ツ)_/¯
-fn home_dir() -> PathBuf {
-    home::home_dir().expect("Cannot get your home directory!")
+fn template(
+    draft: &str,
+    base_args: &BaseArgs,
+    credentials: &Credentials,
+    args: &Args,
+    tweets: &[String],
+) -> String {
+    let delimiter = delimiter(base_args);
+    let mut lines = vec![
+        draft.to_string(),
+        String::new(),
+        String::from("# Write your tweet above. Lines starting with \"# \" are ignored, and an empty file cancels."),
+        format!(
+            "# Separate the tweets of a thread with a line containing just {}",
+            delimiter
+        ),
+        String::from("#"),
+        format!(
+            "# Posting as @{} [{}]",
+            credentials.handle,
+            credentials::profile_name(base_args)
+        ),
+    ];
+    if let Some(ref id) = args.in_reply_to_tweet_id {
+        lines.push(format!("# In reply to {}", id));
+    }
+    if let Some(ref id) = args.quote_tweet_id {
+        lines.push(format!("# Quoting {}", id));
+    }
+    if let Some(ref poll) = args.poll {
+        lines.push(format!("# With a poll: {}", poll.options.join(" / ")));
+    }
+    lines.push(format!(
+        "# Up to {} characters a tweet. Links count as 23, emoji and CJK characters as 2",
+        text::MAX_LENGTH
+    ));
+    if base_args.get_flag("split", "split") {
+        lines.push(String::from(
+            "# Anything longer is split into a thread (--split)",
+        ));
+    }
+    // Where the last draft stood, so there's something to go on when it needs trimming
+    if !tweets.is_empty() {
+        lines.push(String::from("#"));
+        lines.extend(
+            text::summary(tweets)
+                .into_iter()
+                .map(|line| format!("# {}", line)),
+        );
+    }
+    lines.join("\n") + "\n"
 }
 
-fn create_or_open_history() -> Result<File, std::io::Error> {
-    let mut path = PathBuf::from(home_dir());
-    path.push(".twitter_history.toml");
-    path = fs::canonicalize(&path)?;
-    OpenOptions::new().write(true).create(true).open(path)
+fn preview(tweets: &[String]) {
+    for (tweet, summary) in tweets.iter().zip(text::summary(tweets)) {
+        eprintln!("---------------------------------\n");
+        eprintln!("{}\n", tweet);
+        eprintln!("{}\n", summary);
+    }
 }
 
-fn append_response_to_history(response: TwitterCreateResponseData) -> Result<(), TwitterError> {
-    let history = create_or_open_history()?;
-    // let mut contents = String::new();
-    // file.read_to_string(&mut contents)?;
-    // match contents {
-
-    //     let contents = toml::to_string(HistoryFile { tweets: vec![response] })?;
-    //     fs::write(path, contents)?;
-    // }
+// Back and forth with the editor until the tweets are ready to go, or None if it's called off
+fn compose(
+    base_args: &BaseArgs,
+    credentials: &Credentials,
+    args: &Args,
+) -> Result<Option<Vec<String>>, TwitterError> {
+    let delimiter = delimiter(base_args);
+    let mut draft = args.messages.join(&format!("\n{}\n", delimiter));
+    loop {
+        let tweets = arrange(segments(&draft, &delimiter), base_args);
+        let saved = editor::edit(&template(&draft, base_args, credentials, args, &tweets))?;
+        draft = saved
+            .lines()
+            .filter(|line| !is_comment(line))
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim()
+            .to_string();
+        let tweets = arrange(segments(&draft, &delimiter), base_args);
+        if tweets.is_empty() {
+            return Ok(None);
+        }
+        preview(&tweets);
+        let problems = text::problems(&tweets);
+        if problems.is_empty() {
+            match prompt::choose("Post it? [y]es, [e]dit, [N]o")?.as_str() {
+                "y" | "yes" => return Ok(Some(tweets)),
+                "e" | "edit" => continue,
+                _ => return Ok(None),
+            }
+        }
+        eprintln!("{}", problems.join("\n"));
+        match prompt::choose("Back to the editor? [Y/n]")?.as_str() {
+            "n" | "no" => return Ok(None),
+            _ => continue,
+        }
+    }
+}
 
+fn help() -> Result<(), TwitterError> {
+    println!("{}", HELP);
     Ok(())
 }
-*/
 
-pub fn execute(base_args: &BaseArgs) -> Result<(), TwitterError> {
+fn execute(base_args: &BaseArgs, post_or_quote: Action) -> Result<(), TwitterError> {
     if base_args.is_requesting_help() {
         return help();
     }
-    let args = parse(&base_args)?;
+    let mut args = parse(base_args, &post_or_quote)?;
+    let output = Format::parse(base_args)?;
     let credentials = credentials::get(base_args)?;
     base_args.debug(&credentials);
+    if args.edit {
+        match compose(base_args, &credentials, &args)? {
+            Some(messages) => args.messages = messages,
+            None => {
+                eprintln!("Not posting anything");
+                return Ok(());
+            }
+        }
+    }
     let handle = String::from(&credentials.handle);
 
-    let client = twitter::Client::new(&credentials, base_args);
-    let mut response = client.post_v2(&args.messages[0], &args.in_reply_to_tweet_id)?;
-    let first_tweet_id = String::from(&response.id);
-    println!(
-        "Posted tweet {} - https://twitter.com/{}/status/{}",
-        response.id, handle, response.id
-    );
+    media::validate(&args.media, args.messages.len())?;
+
+    // A thread that dies halfway through is worse than a slow one, so threads wait out rate limits by default
+    let mut client = twitter::Client::new(&credentials, base_args);
+    if args.messages.len() > 1 && base_args.get_option::<u32>("retry", "retry").is_none() {
+        client = client.retries(THREAD_RETRIES);
+    }
+    let record =
+        |response: &TwitterCreateResponseData, in_reply_to_id: &Option<String>| PostRecord {
+            id: response.id.to_string(),
+            url: format!("https://twitter.com/{}/status/{}", handle, response.id),
+            text: response.text.to_string(),
+            in_reply_to_id: in_reply_to_id.clone(),
+        };
+    let mut records = Vec::new();
+
+    // Replying to something we posted earlier carries on that thread, otherwise this starts a new one
+    let profile = credentials::profile_name(base_args);
+    let parent_root_id = match (&args.in_reply_to_tweet_id, args.history) {
+        (Some(id), true) => history::read(&profile)
+            .unwrap_or_default()
+            .into_iter()
+            .find(|entry| &entry.id == id)
+            .map(|entry| entry.thread_root_id),
+        _ => None,
+    };
+    let remember = |record: &PostRecord, thread_root_id: &str| {
+        if !args.history {
+            return;
+        }
+        let entry = HistoryRecord {
+            id: record.id.to_string(),
+            url: record.url.to_string(),
+            text: record.text.to_string(),
+            profile: profile.to_string(),
+            handle: handle.to_string(),
+            posted_at: time::now_rfc3339(),
+            in_reply_to_id: record.in_reply_to_id.clone(),
+            thread_root_id: thread_root_id.to_string(),
+        };
+        // The tweet is out there either way, so don't fail the post over it
+        if let Err(err) = history::append(&entry) {
+            eprintln!("Could not add tweet {} to your history: {}", record.id, err);
+        }
+    };
 
-    for message in args.messages[1..].iter() {
-        response = client.post_v2(message, &Some(response.id))?;
+    // Everything is uploaded first, so a bad file can't leave half a thread behind
+    let mut media_ids = vec![Vec::new(); args.messages.len()];
+    for attachment in args.media.iter() {
+        media_ids[attachment.tweet].push(client.upload_media(attachment)?);
+    }
+    // Quotes, polls and places go on the first tweet of a thread, who can reply goes for all of them
+    let tweet = |i: usize, in_reply_to_tweet_id: &Option<String>| NewTweet {
+        text: args.messages[i].to_string(),
+        in_reply_to_tweet_id: in_reply_to_tweet_id.clone(),
+        media_ids: media_ids[i].clone(),
+        quote_tweet_id: args.quote_tweet_id.clone().filter(|_| i == 0),
+        poll: args.poll.clone().filter(|_| i == 0),
+        reply_settings: args.reply_settings,
+        for_super_followers_only: args.super_followers_only,
+        place_id: args.place_id.clone().filter(|_| i == 0),
+    };
+
+    let mut response = client.post_v2(&tweet(0, &args.in_reply_to_tweet_id))?;
+    let first_tweet_id = String::from(&response.id);
+    let thread_root_id = parent_root_id.unwrap_or_else(|| first_tweet_id.clone());
+    records.push(record(&response, &args.in_reply_to_tweet_id));
+    remember(&records[records.len() - 1], &thread_root_id);
+    if output.is_human() {
         println!(
             "Posted tweet {} - https://twitter.com/{}/status/{}",
             response.id, handle, response.id
         );
     }
 
+    for i in 1..args.messages.len() {
+        let previous_id = response.id;
+        response = match client.post_v2(&tweet(i, &Some(previous_id.clone()))) {
+            Ok(response) => response,
+            Err(err) => {
+                // Whatever made it out is still worth knowing about
+                if !output.is_human() {
+                    output.print(&records)?;
+                }
+                eprintln!(
+                    "Thread interrupted after tweet {} ({} of {} posted)",
+                    previous_id,
+                    i,
+                    args.messages.len()
+                );
+                if let Some(rate_limit) = client.rate_limit() {
+                    eprintln!("Rate limit: {}", rate_limit);
+                }
+                eprintln!(
+                    "To finish the thread, post the remaining messages with --reply-id {}",
+                    previous_id
+                );
+                return Err(err);
+            }
+        };
+        records.push(record(&response, &Some(previous_id)));
+        remember(&records[records.len() - 1], &thread_root_id);
+        if output.is_human() {
+            println!(
+                "Posted tweet {} - https://twitter.com/{}/status/{}",
+                response.id, handle, response.id
+            );
+        }
+    }
+
+    if !output.is_human() {
+        return output.print(&records);
+    }
     if args.messages.len() > 1 {
         println!(
             "Thread posted {} - https://twitter.com/{}/status/{}",
@@ -121,3 +558,11 @@ pub fn execute(base_args: &BaseArgs) -> Result<(), TwitterError> {
 
     Ok(())
 }
+
+pub fn execute_post(base_args: &BaseArgs) -> Result<(), TwitterError> {
+    execute(base_args, Action::Post)
+}
+
+pub fn execute_quote(base_args: &BaseArgs) -> Result<(), TwitterError> {
+    execute(base_args, Action::Quote)
+}
@@ -0,0 +1,408 @@
+use super::super::args::BaseArgs;
+use super::super::credentials::{self, Credentials};
+use super::super::error::TwitterError;
+use super::super::history;
+use super::super::prompt;
+use std::path::PathBuf;
+use toml::value::Table;
+use toml::Value;
+
+const HELP: &str = "Manage the profiles in your credentials file!\n
+Usage: tw profile [list]
+       tw profile show [name]
+       tw profile add <name>
+       tw profile remove <name> [--yes]
+       tw profile rename <name> <new name>
+       tw profile default [name]
+       tw profile encrypt
+       tw profile decrypt
+
+Subcommands:
+    list
+        List every profile, with its handle and (masked) keys. The default profile is marked with a *.
+    show [name]
+        Show a single profile. Defaults to the default profile.
+    add <name>
+        Add a profile, asking for each value. Use `tw login -p <name>` if you'd rather log in with your browser.
+    remove <name>
+        Remove a profile. Asks before doing it, unless you pass --yes.
+    rename <name> <new name>
+        Rename a profile. Its history (~/.twitter_history/<name>.jsonl, see `tw history`) is renamed along with it.
+    default [name]
+        Make <name> the profile used when you don't pass --profile. Prints the current default if no name is given.
+    encrypt
+        Encrypt the whole credentials file with a passphrase. From then on, tw asks for the passphrase (or reads
+        it from TW_PASSPHRASE) whenever it needs your credentials, and keeps the file encrypted when it updates it.
+    decrypt
+        Turn an encrypted credentials file back into plain TOML.
+
+Every other entry in your credentials file is left alone.
+
+Options:
+    -y, --yes
+        Don't ask for confirmation before removing a profile.
+    -c, --credentials <name>
+        The file name or path to use for the credentials file.
+        Default: ~/.twitter_credentials.toml
+    --debug
+        Print debug messages.
+
+Examples:
+    See which accounts you have set up:
+        tw profile
+    Add an alt:
+        tw profile add alt1
+    Post from your alt without passing -p every time:
+        tw profile default alt1
+    Stop keeping your secrets in plaintext:
+        tw profile encrypt
+";
+
+fn help() -> Result<(), TwitterError> {
+    println!("{}", HELP);
+    Ok(())
+}
+
+// Keeps just enough of a key to tell two of them apart
+pub fn mask(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    match chars.len() {
+        0 => String::from("(empty)"),
+        len if len <= 8 => "*".repeat(len),
+        len => format!(
+            "{}{}",
+            "*".repeat(len - 4),
+            chars[len - 4..].iter().collect::<String>()
+        ),
+    }
+}
+
+fn name_at(base_args: &BaseArgs, position: usize) -> Result<String, TwitterError> {
+    base_args
+        .get_position::<String>(position)
+        .ok_or_else(|| TwitterError::MissingArgument(String::from("profile name")))
+}
+
+fn current_default(table: &Table) -> String {
+    credentials::default_profile(table).unwrap_or_else(|| String::from("default"))
+}
+
+fn find<'t>(table: &'t Table, name: &str) -> Result<&'t Value, TwitterError> {
+    table
+        .get(name)
+        .filter(|value| value.is_table())
+        .ok_or_else(|| TwitterError::ProfileNotFound(name.to_string()))
+}
+
+fn print_profile(name: &str, credentials: &Credentials, is_default: bool) {
+    let handle = match credentials.handle.as_str() {
+        "" => String::from("(no handle)"),
+        handle => format!("@{}", handle),
+    };
+    println!("{} {} {}", if is_default { "*" } else { " " }, name, handle);
+    println!("      api_key:      {}", mask(&credentials.api_key));
+    println!("      access_token: {}", mask(&credentials.access_token));
+    if let Some(ref client_id) = credentials.client_id {
+        println!("      client_id:    {}", mask(client_id));
+    }
+    if let Some(ref oauth2) = credentials.oauth2 {
+        println!("      oauth2:       {}", oauth2.scopes.join(" "));
+    }
+}
+
+fn list(path: &PathBuf) -> Result<(), TwitterError> {
+    let table = credentials::read_table(path)?;
+    let profiles = credentials::profiles(&table);
+    if profiles.is_empty() {
+        println!(
+            "No profiles in {:?} yet. Add one with `tw profile add <name>` or `tw login`",
+            path
+        );
+        return Ok(());
+    }
+    let default = current_default(&table);
+    for (name, credentials) in profiles {
+        print_profile(&name, &credentials, name == default);
+    }
+    Ok(())
+}
+
+fn show(base_args: &BaseArgs, path: &PathBuf) -> Result<(), TwitterError> {
+    let table = credentials::read_table(path)?;
+    let default = current_default(&table);
+    let name = base_args
+        .get_position::<String>(2)
+        .unwrap_or(default.clone());
+    let credentials: Credentials = find(&table, &name)?.into();
+    print_profile(&name, &credentials, name == default);
+    Ok(())
+}
+
+fn add(base_args: &BaseArgs, path: &PathBuf) -> Result<(), TwitterError> {
+    let name = name_at(base_args, 2)?;
+    if find(&credentials::read_table(path)?, &name).is_ok() {
+        return Err(TwitterError::Invalid(format!(
+            "Profile {} already exists",
+            name
+        )));
+    }
+    let credentials = Credentials {
+        api_key: prompt::ask_required("API key:")?,
+        api_key_secret: prompt::ask_required("API key secret:")?,
+        access_token: prompt::ask_required("Access token:")?,
+        access_token_secret: prompt::ask_required("Access token secret:")?,
+        handle: prompt::ask_required("Handle (without the @):")?
+            .trim_start_matches('@')
+            .to_string(),
+        ..Default::default()
+    };
+    credentials::write_profile(path, &name, &credentials)?;
+    println!("✅ Added profile [{}] to {:?}", name, path);
+    Ok(())
+}
+
+// Returns whether it was the default profile, which puts the default back to [default]
+fn remove_profile(path: &PathBuf, name: &str) -> Result<bool, TwitterError> {
+    let mut table = credentials::read_table(path)?;
+    find(&table, name)?;
+    table.remove(name);
+    let was_default = credentials::default_profile(&table).as_deref() == Some(name);
+    if was_default {
+        table.remove(credentials::DEFAULT_PROFILE_KEY);
+    }
+    credentials::write_table(path, &table)?;
+    Ok(was_default)
+}
+
+fn remove(base_args: &BaseArgs, path: &PathBuf) -> Result<(), TwitterError> {
+    let name = name_at(base_args, 2)?;
+    find(&credentials::read_table(path)?, &name)?;
+
+    if !base_args.get_flag("yes", "y") && !prompt::confirm(&format!("Remove profile [{}]?", name))?
+    {
+        println!("Leaving [{}] alone", name);
+        return Ok(());
+    }
+
+    if remove_profile(path, &name)? {
+        println!(
+            "[{}] was your default profile, so the default is back to [default]",
+            name
+        );
+    }
+    println!("🗑️ Removed profile [{}]", name);
+    Ok(())
+}
+
+fn rename_profile(path: &PathBuf, name: &str, new_name: &str) -> Result<(), TwitterError> {
+    let mut table = credentials::read_table(path)?;
+    find(&table, name)?;
+    if table.contains_key(new_name) {
+        return Err(TwitterError::Invalid(format!(
+            "Profile {} already exists",
+            new_name
+        )));
+    }
+
+    if let Some(profile) = table.remove(name) {
+        table.insert(new_name.to_string(), profile);
+    }
+    // Keep pointing at the same account
+    if credentials::default_profile(&table).as_deref() == Some(name) {
+        table.insert(
+            credentials::DEFAULT_PROFILE_KEY.to_string(),
+            Value::String(new_name.to_string()),
+        );
+    }
+    credentials::write_table(path, &table)
+}
+
+fn rename(base_args: &BaseArgs, path: &PathBuf) -> Result<(), TwitterError> {
+    let name = name_at(base_args, 2)?;
+    let new_name = name_at(base_args, 3)?;
+    // Checked up front, so the profile isn't renamed out from under its history
+    let new_history = history::path(&new_name);
+    if new_history != history::path(&name) && new_history.exists() {
+        return Err(TwitterError::Invalid(format!(
+            "There's already a history for [{}] at {}. Move it out of the way first",
+            new_name,
+            new_history.display()
+        )));
+    }
+    rename_profile(path, &name, &new_name)?;
+    history::rename(&name, &new_name)?;
+    println!("✅ Renamed profile [{}] to [{}]", name, new_name);
+    Ok(())
+}
+
+fn set_default_profile(path: &PathBuf, name: &str) -> Result<(), TwitterError> {
+    let mut table = credentials::read_table(path)?;
+    find(&table, name)?;
+
+    // [default] is what we fall back to anyway, no need to spell it out
+    if name == "default" {
+        table.remove(credentials::DEFAULT_PROFILE_KEY);
+    } else {
+        table.insert(
+            credentials::DEFAULT_PROFILE_KEY.to_string(),
+            Value::String(name.to_string()),
+        );
+    }
+    credentials::write_table(path, &table)
+}
+
+fn set_default(base_args: &BaseArgs, path: &PathBuf) -> Result<(), TwitterError> {
+    let name = match base_args.get_position::<String>(2) {
+        Some(name) => name,
+        None => {
+            println!("{}", current_default(&credentials::read_table(path)?));
+            return Ok(());
+        }
+    };
+    set_default_profile(path, &name)?;
+    println!("✅ [{}] is now your default profile", name);
+    Ok(())
+}
+
+fn encrypt(path: &PathBuf) -> Result<(), TwitterError> {
+    credentials::encrypt(path)?;
+    println!(
+        "🔒 Encrypted {:?}. Don't lose that passphrase, there's no getting it back",
+        path
+    );
+    Ok(())
+}
+
+fn decrypt(path: &PathBuf) -> Result<(), TwitterError> {
+    credentials::decrypt(path)?;
+    println!("🔓 Decrypted {:?}", path);
+    Ok(())
+}
+
+pub fn execute(base_args: &BaseArgs) -> Result<(), TwitterError> {
+    if base_args.is_requesting_help() {
+        return help();
+    }
+    let path = credentials::path(base_args);
+    let subcommand = base_args
+        .get_position::<String>(1)
+        .unwrap_or_else(|| String::from("list"));
+
+    match subcommand.as_str() {
+        "list" | "ls" => list(&path),
+        "show" => show(base_args, &path),
+        "add" => add(base_args, &path),
+        "remove" | "rm" => remove(base_args, &path),
+        "rename" | "mv" => rename(base_args, &path),
+        "default" => set_default(base_args, &path),
+        "encrypt" => encrypt(&path),
+        "decrypt" => decrypt(&path),
+        _ => {
+            println!("Unknown subcommand: {}", subcommand);
+            help()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    fn profile(handle: &str) -> Credentials {
+        Credentials {
+            api_key: format!("{} key", handle),
+            handle: handle.to_string(),
+            ..Default::default()
+        }
+    }
+
+    // Profile names in the file, and its default_profile
+    fn contents(path: &PathBuf) -> (Vec<String>, Option<String>) {
+        let table = credentials::read_table(path).unwrap();
+        let names = credentials::profiles(&table)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        (names, credentials::default_profile(&table))
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn check(path: &PathBuf) {
+        for name in ["default", "alt1", "alt2"] {
+            credentials::write_profile(path, name, &profile(name)).unwrap();
+        }
+        let mut table = credentials::read_table(path).unwrap();
+        table.insert(String::from("unrelated"), Value::Integer(1));
+        credentials::write_table(path, &table).unwrap();
+
+        set_default_profile(path, "alt1").unwrap();
+        assert_eq!(
+            contents(path),
+            (
+                names(&["alt1", "alt2", "default"]),
+                Some(String::from("alt1"))
+            )
+        );
+        assert!(set_default_profile(path, "nope").is_err());
+
+        // The default follows the profile around
+        rename_profile(path, "alt1", "main").unwrap();
+        assert_eq!(
+            contents(path),
+            (
+                names(&["alt2", "default", "main"]),
+                Some(String::from("main"))
+            )
+        );
+        let table = credentials::read_table(path).unwrap();
+        let main: Credentials = find(&table, "main").unwrap().into();
+        assert_eq!(main.handle, "alt1");
+        assert!(rename_profile(path, "main", "alt2").is_err());
+        assert!(rename_profile(path, "nope", "other").is_err());
+
+        assert!(!remove_profile(path, "alt2").unwrap());
+        assert!(remove_profile(path, "alt2").is_err());
+        assert!(remove_profile(path, "main").unwrap());
+        assert_eq!(contents(path), (names(&["default"]), None));
+
+        set_default_profile(path, "default").unwrap();
+        assert_eq!(contents(path), (names(&["default"]), None));
+        // Anything else in the file is left alone
+        let table = credentials::read_table(path).unwrap();
+        assert_eq!(table.get("unrelated"), Some(&Value::Integer(1)));
+    }
+
+    #[test]
+    fn plain_and_encrypted() {
+        let dir = env::temp_dir().join(format!("tw-test-profiles-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let plain = dir.join("plain.toml");
+        check(&plain);
+        assert!(fs::read_to_string(&plain).unwrap().contains("[default]"));
+
+        // Every read and write of an encrypted file runs the KDF, so this sticks to one of each
+        env::set_var("TW_PASSPHRASE", "correct horse battery staple");
+        let encrypted = dir.join("encrypted.toml");
+        for name in ["default", "alt1", "alt2"] {
+            credentials::write_profile(&plain, name, &profile(name)).unwrap();
+        }
+        set_default_profile(&plain, "alt1").unwrap();
+        fs::rename(&plain, &encrypted).unwrap();
+        credentials::encrypt(&encrypted).unwrap();
+        rename_profile(&encrypted, "alt1", "main").unwrap();
+        assert!(!remove_profile(&encrypted, "alt2").unwrap());
+        set_default_profile(&encrypted, "default").unwrap();
+        assert_eq!(contents(&encrypted), (names(&["default", "main"]), None));
+        let raw = fs::read_to_string(&encrypted).unwrap();
+        assert!(raw.contains("ciphertext"));
+        assert!(!raw.contains("[default]"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,93 @@
+use super::super::args::BaseArgs;
+use super::super::credentials;
+use super::super::error::TwitterError;
+use super::super::output::{Format, RetweetRecord};
+use super::super::twitter::{self, ids};
+
+const HELP: &str = "Retweet (or unretweet) a tweet!\n
+Usage: tw retweet <id or url> [OPTIONS]
+       tw unretweet <id or url> [OPTIONS]
+
+To add something of your own, see `tw quote`.
+
+Options:
+    -o, --output <format>
+        Print json, jsonl or csv instead of the usual human friendly output.
+    --format <template>
+        Print the result with a template, e.g. '{id} {retweeted}', or the name of a template in ~/.twitter_config.toml.
+    -p, --profile <name>
+        The name of the profile to use.
+        Must correspond to an entry in your credentials file (~/.twitter_credentials.toml by default).
+    -c, --credentials <name>
+        The file name or path to use for the credentials file.
+        Default: ~/.twitter_credentials.toml
+    --debug
+        Print debug messages.
+
+Examples:
+    Retweet a single tweet:
+        tw retweet 12345666
+    Retweet from an alt account:
+        tw retweet 12345666 --profile alt1
+    Changed your mind:
+        tw unretweet 12345666
+";
+
+struct Args {
+    id: String,
+}
+
+fn parse(args: &BaseArgs) -> Result<Args, TwitterError> {
+    match args.get_position::<String>(1) {
+        Some(id) if !id.is_empty() => Ok(Args {
+            id: ids::tweet_id(&id)?,
+        }),
+        _ => Err(TwitterError::MissingArgument("id".to_string())),
+    }
+}
+
+fn help() -> Result<(), TwitterError> {
+    println!("{}", HELP);
+    Ok(())
+}
+
+enum Action {
+    Retweet,
+    Unretweet,
+}
+
+fn execute(base_args: &BaseArgs, retweet_or_unretweet: Action) -> Result<(), TwitterError> {
+    if base_args.is_requesting_help() {
+        return help();
+    }
+    let args = parse(base_args)?;
+    let output = Format::parse(base_args)?;
+    let credentials = credentials::get(base_args)?;
+    base_args.debug(&credentials);
+
+    let client = twitter::Client::new(&credentials, base_args);
+    let me = client.me()?;
+    let response = match retweet_or_unretweet {
+        Action::Retweet => client.retweet_v2(&me.id, &args.id)?,
+        Action::Unretweet => client.unretweet_v2(&me.id, &args.id)?,
+    };
+    if !output.is_human() {
+        output.print_one(&RetweetRecord {
+            id: args.id,
+            retweeted: response.retweeted,
+        })?;
+    } else if response.retweeted {
+        println!("Retweeted tweet id: {}", args.id);
+    } else {
+        println!("Unretweeted tweet id: {}", args.id);
+    }
+    Ok(())
+}
+
+pub fn execute_retweet(base_args: &BaseArgs) -> Result<(), TwitterError> {
+    execute(base_args, Action::Retweet)
+}
+
+pub fn execute_unretweet(base_args: &BaseArgs) -> Result<(), TwitterError> {
+    execute(base_args, Action::Unretweet)
+}
@@ -0,0 +1,304 @@
+use super::super::args::BaseArgs;
+use super::super::credentials;
+use super::super::error::TwitterError;
+use super::super::output::Format;
+use super::super::stats::{self, Options, Period, Report};
+use super::super::twitter::{self, TwitterHomeItem, TwitterResponse, TwitterUser};
+use super::home::{start_time, PAGINATION_RETRIES};
+use serde_json::json;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+
+const HELP: &str = "See how your tweets are doing!\n
+Usage: tw stats [OPTIONS]
+
+Fetches your tweets (or reads the files written by `tw home --dump`) and reports totals, averages, your top tweets
+by likes, retweets, replies and quotes, and the average engagement (likes + retweets + replies + quotes) per tweet
+by weekday, by hour and over time.
+
+Options:
+    --dumps
+        Read every ~/home-<millis>.json written by `tw home --dump` instead of fetching. Tweets in more than one file are
+        only counted once.
+    --collate
+        With --dumps, also write all of them into a single ~/home-collated.json.
+    --file <path>
+        Read a single `tw home --dump` file instead of fetching.
+    --limit <n>
+        Only fetch your last <n> tweets. Twitter only goes back 3200 tweets.
+    --since <date>
+        Only fetch tweets from <date> onwards, e.g. 2023-01-31 or 2023-01-31T12:00:00Z.
+    --top <n> (default: 5)
+        How many tweets to list for each metric.
+    --trend <day|week|month> (default: month)
+        The period to group tweets by for the trend.
+    --utc-offset <offset> (default: +00:00)
+        Your time zone for the weekday and hour charts, e.g. +02:00 or -5.
+    --dump
+        Write the fetched tweets to a single file, for next time.
+    -o, --output <format>
+        Print json instead of the usual human friendly report.
+    -p, --profile <name>
+        The name of the profile to use.
+        Must correspond to an entry in your credentials file (~/.twitter_credentials.toml by default).
+    -c, --credentials <name>
+        The file name or path to use for the credentials file.
+        Default: ~/.twitter_credentials.toml
+    --debug
+        Print debug messages.
+
+Examples:
+    Stats for all your (recent) tweets:
+        tw stats
+    How did this year go, in Berlin time:
+        tw stats --since 2023-01-01 --utc-offset +01:00
+    Crunch what you've already dumped, week by week:
+        tw stats --dumps --trend week
+";
+
+enum Source {
+    Fetch,
+    Dumps { collate: bool },
+    File(PathBuf),
+}
+
+struct Args {
+    source: Source,
+    limit: Option<usize>,
+    since: Option<String>,
+    options: Options,
+}
+
+fn parse(args: &BaseArgs) -> Result<Args, TwitterError> {
+    let source = match args.get_option::<String>("file", "file") {
+        Some(file) => Source::File(PathBuf::from(file)),
+        None if args.get_flag("dumps", "dumps") => Source::Dumps {
+            collate: args.get_flag("collate", "collate"),
+        },
+        None => Source::Fetch,
+    };
+    let limit = match args.get_option::<String>("limit", "limit") {
+        Some(limit) => match limit.parse::<usize>() {
+            Ok(limit) if limit > 0 => Some(limit),
+            _ => {
+                return Err(TwitterError::Invalid(format!(
+                    "--limit must be a positive number, not {}",
+                    limit
+                )))
+            }
+        },
+        None => None,
+    };
+    let since = match args.get_option::<String>("since", "since") {
+        Some(since) => Some(start_time("--since", &since)?),
+        None => None,
+    };
+    let top = match args.get_option::<String>("top", "top") {
+        Some(top) => top
+            .parse::<usize>()
+            .map_err(|_| TwitterError::Invalid(format!("--top must be a number, not {}", top)))?,
+        None => 5,
+    };
+    let period = match args.get_option::<String>("trend", "trend") {
+        Some(period) => Period::parse(&period)?,
+        None => Period::Month,
+    };
+    let utc_offset = match args.get_option::<String>("utc-offset", "utc-offset") {
+        Some(offset) => stats::parse_utc_offset(&offset)?,
+        // Rather than quietly falling back to UTC
+        None if args.get_flag("utc-offset", "utc-offset") => {
+            return Err(TwitterError::Invalid(String::from(
+                "--utc-offset needs a value, e.g. +02:00 or -5",
+            )))
+        }
+        None => 0,
+    };
+    Ok(Args {
+        source,
+        limit,
+        since,
+        options: Options {
+            top,
+            period,
+            utc_offset,
+        },
+    })
+}
+
+fn help() -> Result<(), TwitterError> {
+    println!("{}", HELP);
+    Ok(())
+}
+
+fn read_dump(path: &PathBuf) -> Result<Vec<TwitterHomeItem>, TwitterError> {
+    let text = fs::read_to_string(path)?;
+    // A page without any tweets is dumped without `data`
+    let dump = serde_json::from_str::<TwitterResponse<Option<Vec<TwitterHomeItem>>>>(&text)
+        .map_err(|err| {
+            TwitterError::Invalid(format!(
+                "{} doesn't look like a `tw home --dump` file: {}",
+                path.display(),
+                err
+            ))
+        })?;
+    Ok(dump.data.unwrap_or_default())
+}
+
+// home-<millis>.json, the way `tw home --dump` names them. Not home-collated.json or anything else that happens
+// to be lying around
+fn is_home_dump(name: &str) -> bool {
+    name.strip_prefix("home-")
+        .and_then(|name| name.strip_suffix(".json"))
+        .is_some_and(|millis| !millis.is_empty() && millis.chars().all(|c| c.is_ascii_digit()))
+}
+
+// Every ~/home-<millis>.json, oldest dump first
+fn read_dumps() -> Result<Vec<TwitterHomeItem>, TwitterError> {
+    let dir = home::home_dir().expect("Cannot get your home directory!");
+    let mut paths = fs::read_dir(&dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(is_home_dump)
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+    if paths.is_empty() {
+        return Err(TwitterError::Invalid(format!(
+            "No home dumps in {}. Make some with `tw home --all --dump`",
+            dir.display()
+        )));
+    }
+
+    let mut items = Vec::new();
+    for path in paths.iter() {
+        items.append(&mut read_dump(path)?);
+    }
+    eprintln!("Read {} tweets from {} dumps", items.len(), paths.len());
+    Ok(items)
+}
+
+fn fetch(
+    client: &twitter::Client,
+    me: &TwitterUser,
+    args: &Args,
+) -> Result<Vec<TwitterHomeItem>, TwitterError> {
+    // Progress on one line that keeps getting overwritten, which only makes sense on a terminal
+    let progress = io::stderr().is_terminal();
+    let mut items = Vec::new();
+    for page in client.home_pages(&me.id, 100, args.since.clone(), None) {
+        items.append(&mut page?);
+        if progress {
+            eprint!("\rFetched {} tweets", items.len());
+        }
+        if let Some(limit) = args.limit {
+            if items.len() >= limit {
+                items.truncate(limit);
+                break;
+            }
+        }
+    }
+    if progress {
+        eprintln!();
+    }
+    Ok(items)
+}
+
+// Overlapping dumps would count the same tweet twice
+fn dedupe(items: Vec<TwitterHomeItem>) -> Vec<TwitterHomeItem> {
+    let mut seen = HashSet::new();
+    let mut items = items
+        .into_iter()
+        .filter(|item| seen.insert(item.id.to_string()))
+        .collect::<Vec<_>>();
+    items.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    items
+}
+
+pub fn execute(base_args: &BaseArgs) -> Result<(), TwitterError> {
+    if base_args.is_requesting_help() {
+        return help();
+    }
+    let args = parse(base_args)?;
+    let output = Format::parse(base_args)?;
+    if !output.is_human() && output != Format::Json {
+        return Err(TwitterError::Invalid(
+            "tw stats only prints a report or --output json".to_string(),
+        ));
+    }
+
+    let (items, me) = match args.source {
+        Source::File(ref path) => (dedupe(read_dump(path)?), None),
+        Source::Dumps { collate } => {
+            let items = dedupe(read_dumps()?);
+            if collate {
+                let path = home::home_dir()
+                    .expect("Cannot get your home directory!")
+                    .join("home-collated.json");
+                fs::write(
+                    &path,
+                    serde_json::to_string_pretty(&json!({ "data": items }))?,
+                )?;
+                eprintln!("Collated {} tweets into {}", items.len(), path.display());
+            }
+            (items, None)
+        }
+        Source::Fetch => {
+            let credentials = credentials::get(base_args)?;
+            base_args.debug(&credentials);
+            let mut client = twitter::Client::new(&credentials, base_args).dump_responses(false);
+            if base_args.get_option::<u32>("retry", "retry").is_none() {
+                client = client.retries(PAGINATION_RETRIES);
+            }
+            let me = client.me()?;
+            let items = fetch(&client, &me, &args)?;
+            if base_args.flags.contains_key("dump") {
+                client.dump(
+                    "home",
+                    &serde_json::to_string_pretty(&json!({ "data": items }))?,
+                )?;
+            }
+            (items, Some(me))
+        }
+    };
+
+    let report = Report::new(&items, me.as_ref(), &args.options);
+    match output {
+        Format::Json => output.print_one(&report),
+        _ => {
+            report.display();
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Args, TwitterError> {
+        let args = ["tw", "stats"]
+            .iter()
+            .chain(args)
+            .map(|arg| arg.to_string());
+        parse(&BaseArgs::from_args(args.collect())?)
+    }
+
+    #[test]
+    fn utc_offsets() {
+        let offset = |args: &[&str]| parse_args(args).map(|args| args.options.utc_offset);
+        assert_eq!(offset(&[]).unwrap(), 0);
+        assert_eq!(offset(&["--utc-offset", "+02:00"]).unwrap(), 120);
+        assert_eq!(
+            offset(&["--utc-offset", "-05:00", "-o", "json"]).unwrap(),
+            -300
+        );
+        assert_eq!(offset(&["--utc-offset", "-5", "--dumps"]).unwrap(), -300);
+        assert!(offset(&["--utc-offset"]).is_err());
+        assert!(offset(&["--utc-offset", "--dumps"]).is_err());
+    }
+}
@@ -0,0 +1,38 @@
+// ~/.twitter_config.toml, for settings that aren't secrets (so they don't belong in the credentials file).
+// Use TW_CONFIG to point somewhere else.
+//
+// [templates]
+// oneline = "{author.handle}: {text} ({metrics.likes}❤)"
+
+use super::error::TwitterError;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use toml::value::Table;
+use toml::Value;
+
+pub fn path() -> PathBuf {
+    let file = env::var("TW_CONFIG").unwrap_or_else(|_| String::from(".twitter_config.toml"));
+    let mut path = home::home_dir().expect("Cannot get your home directory!");
+    path.push(file);
+    path
+}
+
+// The whole config file, or an empty table if there isn't one
+pub fn read() -> Result<Table, TwitterError> {
+    match fs::read_to_string(path()) {
+        Ok(contents) => Ok(toml::from_str(&contents)?),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Table::new()),
+        Err(err) => Err(TwitterError::Io(err)),
+    }
+}
+
+// A named `--format` template from the [templates] table
+pub fn template(name: &str) -> Result<Option<String>, TwitterError> {
+    Ok(read()?
+        .get("templates")
+        .and_then(|templates| templates.get(name))
+        .and_then(Value::as_str)
+        .map(String::from))
+}
@@ -1,49 +1,230 @@
 use super::args::BaseArgs;
+use super::crypto::{self, Sealed};
 use super::error::TwitterError;
+use super::private;
+use super::prompt;
 use serde::{Deserialize, Serialize};
+use std::env;
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use toml::value::Table;
 use toml::Value;
 
 struct Args {
     credentials_file: String,
     profile: Option<String>,
+    // Where the two values above came from, for --debug
+    credentials_file_source: Source,
+    profile_source: Source,
 }
 
+// Flags win over env vars, which win over whatever the credentials file says
 fn parse(args: &BaseArgs) -> Args {
-    let credentials_file = args.get(
-        "credentials",
-        "c",
-        String::from(".twitter_credentials.toml"),
-    );
-    let profile = args.get_option("profile", "p");
+    let (credentials_file, credentials_file_source) =
+        match args.get_option::<String>("credentials", "c") {
+            Some(file) => (file, Source::Flag("--credentials")),
+            None => match env_var("TW_CREDENTIALS") {
+                Some(file) => (file, Source::Env("TW_CREDENTIALS")),
+                None => (String::from(".twitter_credentials.toml"), Source::Default),
+            },
+        };
+    let (profile, profile_source) = match args.get_option::<String>("profile", "p") {
+        Some(profile) => (Some(profile), Source::Flag("--profile")),
+        None => match env_var("TW_PROFILE") {
+            Some(profile) => (Some(profile), Source::Env("TW_PROFILE")),
+            None => (None, Source::Default),
+        },
+    };
     Args {
         credentials_file,
         profile,
+        credentials_file_source,
+        profile_source,
+    }
+}
+
+// Env vars that override (or stand in for) the matching key in a profile
+const ENV_VARS: [(&str, &str); 5] = [
+    ("api_key", "TW_API_KEY"),
+    ("api_key_secret", "TW_API_KEY_SECRET"),
+    ("access_token", "TW_ACCESS_TOKEN"),
+    ("access_token_secret", "TW_ACCESS_TOKEN_SECRET"),
+    ("handle", "TW_HANDLE"),
+];
+
+// Keys a profile's secret_command is allowed to supply
+const SECRET_KEYS: [&str; 5] = [
+    "api_key",
+    "api_key_secret",
+    "access_token",
+    "access_token_secret",
+    "client_secret",
+];
+
+fn env_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+// Where a credential came from. Printed with --debug instead of the value itself
+enum Source {
+    Flag(&'static str),
+    Env(&'static str),
+    SecretCommand,
+    File,
+    Default,
+    Missing,
+}
+
+impl fmt::Debug for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::Flag(flag) => write!(f, "flag {}", flag),
+            Source::Env(name) => write!(f, "env {}", name),
+            Source::SecretCommand => write!(f, "secret_command"),
+            Source::File => write!(f, "credentials file"),
+            Source::Default => write!(f, "default"),
+            Source::Missing => write!(f, "missing"),
+        }
+    }
+}
+
+struct Sources {
+    credentials_file: (PathBuf, Source),
+    profile: (String, Source),
+    api_key: Source,
+    api_key_secret: Source,
+    access_token: Source,
+    access_token_secret: Source,
+    handle: Source,
+    client_secret: Source,
+}
+
+impl fmt::Debug for Sources {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CredentialSources")
+            .field("credentials_file", &self.credentials_file)
+            .field("profile", &self.profile)
+            .field("api_key", &self.api_key)
+            .field("api_key_secret", &self.api_key_secret)
+            .field("access_token", &self.access_token)
+            .field("access_token_secret", &self.access_token_secret)
+            .field("handle", &self.handle)
+            .field("client_secret", &self.client_secret)
+            .finish()
+    }
+}
+
+impl Sources {
+    fn set(&mut self, key: &str, source: Source) {
+        match key {
+            "api_key" => self.api_key = source,
+            "api_key_secret" => self.api_key_secret = source,
+            "access_token" => self.access_token = source,
+            "access_token_secret" => self.access_token_secret = source,
+            "handle" => self.handle = source,
+            "client_secret" => self.client_secret = source,
+            _ => {}
+        }
     }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Default)]
 pub struct Credentials {
     pub api_key: String,
     pub api_key_secret: String,
     pub access_token: String,
     pub access_token_secret: String,
     pub handle: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_base: Option<String>,
+    // OAuth 2.0 app client, for endpoints that need a user token with scopes. See `tw login --oauth2`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    // Has to stay last, TOML wants tables after plain values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oauth2: Option<OAuth2Token>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct OAuth2Token {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    pub expires_at: u64, // epoch seconds
+    pub scopes: Vec<String>,
+}
+
+// --debug shows whether each secret is set, never the secret itself, so the output is safe to paste in an issue
+fn redacted(value: &str) -> fmt::Arguments<'static> {
+    if value.is_empty() {
+        format_args!("(empty)")
+    } else {
+        format_args!("(redacted)")
+    }
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("api_key", &redacted(&self.api_key))
+            .field("api_key_secret", &redacted(&self.api_key_secret))
+            .field("access_token", &redacted(&self.access_token))
+            .field("access_token_secret", &redacted(&self.access_token_secret))
+            .field("handle", &self.handle)
+            .field("api_base", &self.api_base)
+            .field("client_id", &self.client_id)
+            .field(
+                "client_secret",
+                &self.client_secret.as_deref().map(redacted),
+            )
+            .field("oauth2", &self.oauth2)
+            .finish()
+    }
+}
+
+impl fmt::Debug for OAuth2Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OAuth2Token")
+            .field("access_token", &redacted(&self.access_token))
+            .field(
+                "refresh_token",
+                &self.refresh_token.as_deref().map(redacted),
+            )
+            .field("expires_at", &self.expires_at)
+            .field("scopes", &self.scopes)
+            .finish()
+    }
 }
 
 impl From<&Value> for Credentials {
     fn from(value: &Value) -> Credentials {
         match value {
             Value::Table(fields) => Credentials {
-                api_key: fields["api_key"].as_str().unwrap_or("").to_string(),
-                api_key_secret: fields["api_key_secret"].as_str().unwrap_or("").to_string(),
-                access_token: fields["access_token"].as_str().unwrap_or("").to_string(),
-                access_token_secret: fields["access_token_secret"]
-                    .as_str()
-                    .unwrap_or("")
-                    .to_string(),
-                handle: fields["handle"].as_str().unwrap_or("").to_string(),
+                api_key: string_field(fields, "api_key"),
+                api_key_secret: string_field(fields, "api_key_secret"),
+                access_token: string_field(fields, "access_token"),
+                access_token_secret: string_field(fields, "access_token_secret"),
+                handle: string_field(fields, "handle"),
+                api_base: fields
+                    .get("api_base")
+                    .and_then(|value| value.as_str())
+                    .map(String::from),
+                client_id: fields
+                    .get("client_id")
+                    .and_then(|value| value.as_str())
+                    .map(String::from),
+                client_secret: fields
+                    .get("client_secret")
+                    .and_then(|value| value.as_str())
+                    .map(String::from),
+                oauth2: fields
+                    .get("oauth2")
+                    .and_then(|value| value.clone().try_into().ok()),
             },
             _ => {
                 panic!("Credentials file not formatted correctly! Try using `tw init`")
@@ -52,6 +233,14 @@ impl From<&Value> for Credentials {
     }
 }
 
+fn string_field(fields: &Table, key: &str) -> String {
+    fields
+        .get(key)
+        .and_then(|value| value.as_str())
+        .unwrap_or("")
+        .to_string()
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct CredentialsFile {
     pub default: Credentials,
@@ -62,37 +251,535 @@ fn home_dir() -> PathBuf {
         .expect("Cannot get your home directory! Please pass the path to your .twitter_credentials.toml manually using -c or --credentials")
 }
 
-fn is_any_empty(credentials: &Credentials) -> bool {
-    credentials.api_key.is_empty()
-        || credentials.api_key_secret.is_empty()
-        || credentials.access_token.is_empty()
-        || credentials.access_token_secret.is_empty()
-        || credentials.handle.is_empty()
+impl Credentials {
+    fn field(&mut self, key: &str) -> Option<&mut String> {
+        match key {
+            "api_key" => Some(&mut self.api_key),
+            "api_key_secret" => Some(&mut self.api_key_secret),
+            "access_token" => Some(&mut self.access_token),
+            "access_token_secret" => Some(&mut self.access_token_secret),
+            "handle" => Some(&mut self.handle),
+            _ => None,
+        }
+    }
+
+    fn missing_fields(&self) -> Vec<&'static str> {
+        let mut missing = Vec::new();
+        for (key, value) in [
+            ("api_key", &self.api_key),
+            ("api_key_secret", &self.api_key_secret),
+            ("access_token", &self.access_token),
+            ("access_token_secret", &self.access_token_secret),
+            ("handle", &self.handle),
+        ] {
+            if value.is_empty() {
+                missing.push(key);
+            }
+        }
+        missing
+    }
 }
 
-pub fn get(base_args: &BaseArgs) -> Result<Credentials, TwitterError> {
+// Where the credentials file lives, taking --credentials into account.
+// Absolute paths are left alone because `push` replaces the whole path in that case.
+pub fn path(base_args: &BaseArgs) -> PathBuf {
     let args = parse(base_args);
-    let mut path = PathBuf::from(home_dir());
+    let mut path = home_dir();
     path.push(&args.credentials_file);
+    path
+}
+
+// Top level key naming the profile to use when --profile isn't passed. See `tw profile default`
+pub const DEFAULT_PROFILE_KEY: &str = "default_profile";
+
+// The name of the profile selected with --profile (or TW_PROFILE), falling back to the file's default_profile, then "default"
+pub fn profile_name(base_args: &BaseArgs) -> String {
+    match parse(base_args).profile {
+        Some(profile) => profile,
+        None => read_table(&path(base_args))
+            .ok()
+            .and_then(|table| default_profile(&table))
+            .unwrap_or_else(|| String::from("default")),
+    }
+}
+
+pub fn default_profile(table: &Table) -> Option<String> {
+    table
+        .get(DEFAULT_PROFILE_KEY)
+        .and_then(Value::as_str)
+        .map(String::from)
+}
+
+// Every profile in the file, skipping top level keys that aren't tables (like default_profile)
+pub fn profiles(table: &Table) -> Vec<(String, Credentials)> {
+    table
+        .iter()
+        .filter(|(_, value)| value.is_table())
+        .map(|(name, value)| (name.to_string(), value.into()))
+        .collect()
+}
+
+// Top level table that holds the whole file, sealed, once it has been through `tw profile encrypt`
+pub const ENCRYPTED_KEY: &str = "encrypted";
 
-    path = fs::canonicalize(&path)?;
-    let contents = fs::read_to_string(&path)?;
-
-    match &args.profile {
-        Some(profile) => {
-            let credentials: Value = toml::from_str(&contents)?;
-            let profile_credentials: Credentials = credentials
-                .get(&profile)
-                .ok_or(TwitterError::ProfileNotFound(profile.to_string()))?
-                .into();
-            if is_any_empty(&profile_credentials) {
-                panic!("Profile {} has empty fields! Please ensure all values are present and non-empty", profile);
+// Asked for at most once per run, even if the file gets read and written a few times
+static PASSPHRASE: OnceLock<String> = OnceLock::new();
+
+// TW_PASSPHRASE, or ask. `confirm` asks twice, for when a typo would lock you out of the file
+fn passphrase(confirm: bool) -> Result<String, TwitterError> {
+    if let Some(passphrase) = PASSPHRASE.get() {
+        return Ok(passphrase.clone());
+    }
+    let passphrase = match env_var("TW_PASSPHRASE") {
+        Some(passphrase) => passphrase,
+        None => {
+            let passphrase = prompt::ask_secret("Credentials file passphrase:").map_err(|_| {
+                TwitterError::MissingArgument(
+                    "passphrase. Set TW_PASSPHRASE when there's no terminal to ask on".to_string(),
+                )
+            })?;
+            if confirm && prompt::ask_secret("Same passphrase again:")? != passphrase {
+                return Err(TwitterError::Invalid("Passphrases don't match".to_string()));
             }
-            Ok(profile_credentials)
+            passphrase
         }
-        None => {
-            let credentials: CredentialsFile = toml::from_str(&contents)?;
-            Ok(credentials.default)
+    };
+    if passphrase.is_empty() {
+        return Err(TwitterError::MissingArgument("passphrase".to_string()));
+    }
+    Ok(PASSPHRASE.get_or_init(|| passphrase).clone())
+}
+
+fn is_encrypted(table: &Table) -> bool {
+    table
+        .get(ENCRYPTED_KEY)
+        .and_then(|value| value.get("ciphertext"))
+        .is_some()
+}
+
+// The file as it is on disk, encrypted or not, or an empty table if the file doesn't exist yet
+fn read_raw(path: &PathBuf) -> Result<Table, TwitterError> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(toml::from_str(&contents)?),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Table::new()),
+        Err(err) => Err(TwitterError::Io(err)),
+    }
+}
+
+// Reads the whole credentials file as a TOML table, or an empty one if the file doesn't exist yet.
+// Encrypted files are decrypted on the way in.
+pub fn read_table(path: &PathBuf) -> Result<Table, TwitterError> {
+    let table = read_raw(path)?;
+    if !is_encrypted(&table) {
+        return Ok(table);
+    }
+    let sealed: Sealed = table[ENCRYPTED_KEY].clone().try_into()?;
+    let plaintext = crypto::open(&sealed, &passphrase(false)?)?;
+    Ok(toml::from_str(&String::from_utf8_lossy(&plaintext))?)
+}
+
+fn to_toml(table: &Table) -> Result<String, TwitterError> {
+    // TOML wants top level values (like default_profile) before any tables, but the keys come out sorted
+    let (tables, values): (Table, Table) = table
+        .clone()
+        .into_iter()
+        .partition(|(_, value)| value.is_table());
+    let mut contents = toml::to_string(&values)?;
+    if !values.is_empty() {
+        contents.push('\n');
+    }
+    contents.push_str(&toml::to_string(&tables)?);
+    Ok(contents)
+}
+
+fn write_encrypted(path: &Path, table: &Table, passphrase: &str) -> Result<(), TwitterError> {
+    let sealed = crypto::seal(to_toml(table)?.as_bytes(), passphrase)?;
+    let mut file = Table::new();
+    file.insert(ENCRYPTED_KEY.to_string(), Value::try_from(sealed)?);
+    private::write(path, to_toml(&file)?)?;
+    Ok(())
+}
+
+// Writes the whole credentials file. If it was encrypted, it stays encrypted.
+pub fn write_table(path: &PathBuf, table: &Table) -> Result<(), TwitterError> {
+    if is_encrypted(&read_raw(path)?) {
+        return write_encrypted(path, table, &passphrase(false)?);
+    }
+    private::write(path, to_toml(table)?)?;
+    Ok(())
+}
+
+pub fn encrypt(path: &PathBuf) -> Result<(), TwitterError> {
+    if !path.exists() {
+        return Err(TwitterError::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No credentials file at {:?}", path),
+        )));
+    }
+    let table = read_raw(path)?;
+    if is_encrypted(&table) {
+        return Err(TwitterError::Invalid(format!(
+            "{:?} is already encrypted",
+            path
+        )));
+    }
+    write_encrypted(path, &table, &passphrase(true)?)
+}
+
+pub fn decrypt(path: &PathBuf) -> Result<(), TwitterError> {
+    if !is_encrypted(&read_raw(path)?) {
+        return Err(TwitterError::Invalid(format!("{:?} isn't encrypted", path)));
+    }
+    let table = read_table(path)?;
+    private::write(path, to_toml(&table)?)?;
+    Ok(())
+}
+
+// Adds or updates a single profile, leaving every other entry in the file alone.
+// Keys in the profile that `Credentials` doesn't know about are kept, too.
+pub fn write_profile(
+    path: &PathBuf,
+    profile: &str,
+    credentials: &Credentials,
+) -> Result<(), TwitterError> {
+    let mut table = read_table(path)?;
+    let mut profile_table = match table.remove(profile) {
+        Some(Value::Table(existing)) => existing,
+        _ => Table::new(),
+    };
+    if let Value::Table(fields) = Value::try_from(credentials)? {
+        profile_table.extend(fields);
+    }
+    table.insert(profile.to_string(), Value::Table(profile_table));
+    write_table(path, &table)
+}
+
+// Sets a single key in a profile (creating the profile if needed), e.g. after refreshing an OAuth 2.0 token
+pub fn write_profile_value(
+    path: &PathBuf,
+    profile: &str,
+    key: &str,
+    value: Value,
+) -> Result<(), TwitterError> {
+    let mut table = read_table(path)?;
+    let profile_table = table
+        .entry(profile.to_string())
+        .or_insert_with(|| Value::Table(Table::new()));
+    match profile_table {
+        Value::Table(profile_table) => {
+            profile_table.insert(key.to_string(), value);
+        }
+        _ => return Err(TwitterError::ProfileNotFound(profile.to_string())),
+    }
+    write_table(path, &table)
+}
+
+// Runs a profile's secret_command (e.g. `pass show twitter/alt1`) and picks secrets out of its stdout.
+// Every line that looks like `key = value` or `key: value` for one of SECRET_KEYS counts, the rest is ignored.
+fn run_secret_command(command: &str) -> Result<Vec<(String, String)>, TwitterError> {
+    let output = if cfg!(windows) {
+        Command::new("cmd").args(["/C", command]).output()?
+    } else {
+        Command::new("sh").args(["-c", command]).output()?
+    };
+    if !output.status.success() {
+        return Err(TwitterError::Auth(format!(
+            "secret_command `{}` failed ({}): {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(parse_secrets(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_secrets(stdout: &str) -> Vec<(String, String)> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let separator = line.find(['=', ':'])?;
+            let key = line[..separator].trim();
+            let value = line[separator + 1..].trim().trim_matches('"');
+            if SECRET_KEYS.contains(&key) && !value.is_empty() {
+                Some((key.to_string(), value.to_string()))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+// Layers the secret_command's secrets and then the env vars that are set, as (key, env var, value), over the profile
+// from the file, and records where every key ended up coming from. With --profile the env vars only fill in keys the
+// profile leaves empty. Returns the (key, env var) pairs that were ignored because of that
+fn merge(
+    mut credentials: Credentials,
+    secrets: Vec<(String, String)>,
+    env: &[(&'static str, &'static str, String)],
+    is_profile_flag: bool,
+    sources: &mut Sources,
+) -> (Credentials, Vec<(&'static str, &'static str)>) {
+    let file_source = |value: &str| match value {
+        "" => Source::Missing,
+        _ => Source::File,
+    };
+    sources.api_key = file_source(&credentials.api_key);
+    sources.api_key_secret = file_source(&credentials.api_key_secret);
+    sources.access_token = file_source(&credentials.access_token);
+    sources.access_token_secret = file_source(&credentials.access_token_secret);
+    sources.handle = file_source(&credentials.handle);
+    sources.client_secret = file_source(credentials.client_secret.as_deref().unwrap_or(""));
+
+    for (key, value) in secrets {
+        match credentials.field(&key) {
+            Some(field) => *field = value,
+            None => credentials.client_secret = Some(value),
+        }
+        sources.set(&key, Source::SecretCommand);
+    }
+
+    let mut ignored = Vec::new();
+    for (key, name, value) in env {
+        if let Some(field) = credentials.field(key) {
+            if is_profile_flag && !field.is_empty() {
+                ignored.push((*key, *name));
+                continue;
+            }
+            *field = value.to_string();
+        }
+        sources.set(key, Source::Env(name));
+    }
+    (credentials, ignored)
+}
+
+// Puts together the credentials for the selected profile. For every key, the first one of these wins:
+//   1. a profile picked with --profile (the flag), from its secret_command or the file
+//   2. TW_API_KEY, TW_API_KEY_SECRET, TW_ACCESS_TOKEN, TW_ACCESS_TOKEN_SECRET, TW_HANDLE
+//   3. the profile's secret_command
+//   4. the profile itself
+// With all five env vars set you don't need a credentials file at all.
+pub fn get(base_args: &BaseArgs) -> Result<Credentials, TwitterError> {
+    let args = parse(base_args);
+    let path = path(base_args);
+    let table = read_table(&path)?;
+    // So a TW_ACCESS_TOKEN exported for CI can't quietly post from somebody else's account
+    let is_profile_flag = matches!(args.profile_source, Source::Flag(_));
+
+    let profile = args
+        .profile
+        .clone()
+        .or_else(|| default_profile(&table))
+        .unwrap_or_else(|| String::from("default"));
+    let profile_source = match args.profile_source {
+        Source::Default if default_profile(&table).is_some() => Source::File,
+        source => source,
+    };
+    let profile_table = table.get(&profile).filter(|value| value.is_table());
+
+    let credentials: Credentials = match profile_table {
+        Some(value) => value.into(),
+        None if args.profile.is_some() => {
+            return Err(TwitterError::ProfileNotFound(profile));
+        }
+        // Nothing on disk, hopefully the env has everything
+        None => Credentials::default(),
+    };
+
+    let secrets = match profile_table
+        .and_then(|value| value.get("secret_command"))
+        .and_then(Value::as_str)
+    {
+        Some(command) => run_secret_command(command)?,
+        None => Vec::new(),
+    };
+    let env = ENV_VARS
+        .iter()
+        .filter_map(|(key, name)| env_var(name).map(|value| (*key, *name, value)))
+        .collect::<Vec<_>>();
+    let mut sources = Sources {
+        credentials_file: (path.clone(), args.credentials_file_source),
+        profile: (profile.clone(), profile_source),
+        api_key: Source::Missing,
+        api_key_secret: Source::Missing,
+        access_token: Source::Missing,
+        access_token_secret: Source::Missing,
+        handle: Source::Missing,
+        client_secret: Source::Missing,
+    };
+    let (credentials, ignored) = merge(credentials, secrets, &env, is_profile_flag, &mut sources);
+    for (key, name) in ignored {
+        base_args.debug(&format!(
+            "Ignoring {}, --profile {} has its own {}",
+            name, profile, key
+        ));
+    }
+    base_args.debug(&sources);
+
+    // A profile that only went through `tw login --oauth2` has no OAuth 1.0a keys, and that's fine
+    let missing = credentials.missing_fields();
+    if credentials.oauth2.is_none() && !missing.is_empty() {
+        if profile_table.is_none() && !path.exists() {
+            return Err(TwitterError::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "No credentials file at {:?}. Try `tw init`, or set {}",
+                    path,
+                    ENV_VARS
+                        .iter()
+                        .map(|(_, name)| *name)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            )));
+        }
+        return Err(TwitterError::Invalid(format!(
+            "Profile {} is missing {}. Fill them in, or set the matching TW_* env vars",
+            profile,
+            missing.join(", ")
+        )));
+    }
+    Ok(credentials)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sources() -> Sources {
+        Sources {
+            credentials_file: (PathBuf::from("credentials.toml"), Source::Default),
+            profile: (String::from("default"), Source::Default),
+            api_key: Source::Missing,
+            api_key_secret: Source::Missing,
+            access_token: Source::Missing,
+            access_token_secret: Source::Missing,
+            handle: Source::Missing,
+            client_secret: Source::Missing,
+        }
+    }
+
+    fn from_file() -> Credentials {
+        Credentials {
+            api_key: String::from("file key"),
+            access_token: String::from("file token"),
+            handle: String::from("file"),
+            ..Default::default()
         }
     }
+
+    fn secrets(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn env() -> Vec<(&'static str, &'static str, String)> {
+        vec![
+            ("api_key", "TW_API_KEY", String::from("env key")),
+            (
+                "api_key_secret",
+                "TW_API_KEY_SECRET",
+                String::from("env secret"),
+            ),
+        ]
+    }
+
+    #[test]
+    fn file_only() {
+        let mut sources = sources();
+        let (credentials, ignored) = merge(from_file(), Vec::new(), &[], false, &mut sources);
+        assert_eq!(credentials.api_key, "file key");
+        assert_eq!(format!("{:?}", sources.api_key), "credentials file");
+        assert_eq!(format!("{:?}", sources.api_key_secret), "missing");
+        assert_eq!(format!("{:?}", sources.client_secret), "missing");
+        assert!(ignored.is_empty());
+    }
+
+    #[test]
+    fn secret_command_beats_the_file() {
+        let mut sources = sources();
+        let secrets = secrets(&[("access_token", "command token"), ("client_secret", "shh")]);
+        let (credentials, _) = merge(from_file(), secrets, &[], false, &mut sources);
+        assert_eq!(credentials.access_token, "command token");
+        assert_eq!(credentials.client_secret.as_deref(), Some("shh"));
+        assert_eq!(credentials.api_key, "file key");
+        assert_eq!(format!("{:?}", sources.access_token), "secret_command");
+        assert_eq!(format!("{:?}", sources.client_secret), "secret_command");
+    }
+
+    #[test]
+    fn env_beats_the_profile() {
+        let mut sources = sources();
+        let secrets = secrets(&[("api_key", "command key")]);
+        let (credentials, ignored) = merge(from_file(), secrets, &env(), false, &mut sources);
+        assert_eq!(credentials.api_key, "env key");
+        assert_eq!(credentials.api_key_secret, "env secret");
+        assert_eq!(credentials.access_token, "file token");
+        assert_eq!(format!("{:?}", sources.api_key), "env TW_API_KEY");
+        assert!(ignored.is_empty());
+    }
+
+    #[test]
+    fn env_without_a_file() {
+        let mut sources = sources();
+        let (credentials, _) = merge(
+            Credentials::default(),
+            Vec::new(),
+            &env(),
+            false,
+            &mut sources,
+        );
+        assert_eq!(credentials.api_key, "env key");
+        assert_eq!(credentials.handle, "");
+        assert_eq!(
+            format!("{:?}", sources.api_key_secret),
+            "env TW_API_KEY_SECRET"
+        );
+        assert_eq!(format!("{:?}", sources.handle), "missing");
+    }
+
+    #[test]
+    fn profile_flag_beats_env() {
+        let mut sources = sources();
+        let secrets = secrets(&[("api_key", "command key")]);
+        let (credentials, ignored) = merge(from_file(), secrets, &env(), true, &mut sources);
+        // The profile's own key, from its secret_command, stays
+        assert_eq!(credentials.api_key, "command key");
+        assert_eq!(format!("{:?}", sources.api_key), "secret_command");
+        // The env still fills in what the profile leaves out
+        assert_eq!(credentials.api_key_secret, "env secret");
+        assert_eq!(
+            format!("{:?}", sources.api_key_secret),
+            "env TW_API_KEY_SECRET"
+        );
+        assert_eq!(ignored, vec![("api_key", "TW_API_KEY")]);
+    }
+
+    #[test]
+    fn secret_command_output() {
+        let stdout = "hunter2\n\
+            api_key = abc\n\
+            api_key_secret: \"quoted\"\n\
+            access_token=with=equals:and:colons\n\
+            access_token_secret =\n\
+            handle = not a secret\n\
+            url: https://example.com\n\
+            client_secret\n\
+            = orphan\n\
+            \n\
+            client_secret : last\n";
+        assert_eq!(
+            parse_secrets(stdout),
+            secrets(&[
+                ("api_key", "abc"),
+                ("api_key_secret", "quoted"),
+                ("access_token", "with=equals:and:colons"),
+                ("client_secret", "last"),
+            ])
+        );
+        assert!(parse_secrets("").is_empty());
+        assert!(parse_secrets("just a password").is_empty());
+    }
 }
@@ -0,0 +1,167 @@
+// Passphrase based encryption for the credentials file.
+// The key comes from Argon2id, the contents are sealed with XChaCha20-Poly1305, so a wrong passphrase
+// (or someone fiddling with the file) is caught instead of decrypting into garbage.
+
+use super::error::TwitterError;
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+
+const VERSION: u32 = 1;
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+// Ties the ciphertext to this format, so it can't be passed off as something else
+const AAD: &[u8] = b"tw credentials v1";
+
+// What ends up in the `[encrypted]` table of the credentials file. Binary values are base64.
+// The Argon2 costs are stored so they can be bumped later without breaking existing files.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Sealed {
+    pub version: u32,
+    pub kdf: String,
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+    pub salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+fn random_bytes<const N: usize>() -> Result<[u8; N], TwitterError> {
+    let mut bytes = [0u8; N];
+    getrandom::getrandom(&mut bytes).map_err(|err| {
+        TwitterError::Invalid(format!("Could not generate random bytes: {}", err))
+    })?;
+    Ok(bytes)
+}
+
+fn derive_key(
+    passphrase: &str,
+    salt: &[u8],
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+) -> Result<[u8; KEY_LEN], TwitterError> {
+    let params = Params::new(m_cost, t_cost, p_cost, Some(KEY_LEN))
+        .map_err(|err| TwitterError::Invalid(format!("Bad Argon2 parameters: {}", err)))?;
+    let mut key = [0u8; KEY_LEN];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| TwitterError::Invalid(format!("Could not derive key: {}", err)))?;
+    Ok(key)
+}
+
+fn decode(field: &str, value: &str) -> Result<Vec<u8>, TwitterError> {
+    base64::decode(value).map_err(|_| {
+        TwitterError::Invalid(format!(
+            "Encrypted credentials file has a broken `{}` value",
+            field
+        ))
+    })
+}
+
+pub fn seal(plaintext: &[u8], passphrase: &str) -> Result<Sealed, TwitterError> {
+    let salt = random_bytes::<SALT_LEN>()?;
+    let nonce = random_bytes::<NONCE_LEN>()?;
+    let (m_cost, t_cost, p_cost) = (
+        Params::DEFAULT_M_COST,
+        Params::DEFAULT_T_COST,
+        Params::DEFAULT_P_COST,
+    );
+    let key = derive_key(passphrase, &salt, m_cost, t_cost, p_cost)?;
+    let ciphertext = XChaCha20Poly1305::new(&key.into())
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: AAD,
+            },
+        )
+        .map_err(|_| TwitterError::Invalid("Could not encrypt credentials".to_string()))?;
+
+    Ok(Sealed {
+        version: VERSION,
+        kdf: "argon2id".to_string(),
+        m_cost,
+        t_cost,
+        p_cost,
+        salt: base64::encode(salt),
+        nonce: base64::encode(nonce),
+        ciphertext: base64::encode(ciphertext),
+    })
+}
+
+pub fn open(sealed: &Sealed, passphrase: &str) -> Result<Vec<u8>, TwitterError> {
+    if sealed.version != VERSION || sealed.kdf != "argon2id" {
+        return Err(TwitterError::Invalid(format!(
+            "Don't know how to decrypt version {} ({}) credentials files. Is tw out of date?",
+            sealed.version, sealed.kdf
+        )));
+    }
+    let salt = decode("salt", &sealed.salt)?;
+    let nonce = decode("nonce", &sealed.nonce)?;
+    let ciphertext = decode("ciphertext", &sealed.ciphertext)?;
+    if nonce.len() != NONCE_LEN {
+        return Err(TwitterError::Invalid(
+            "Encrypted credentials file has a broken `nonce` value".to_string(),
+        ));
+    }
+
+    let key = derive_key(
+        passphrase,
+        &salt,
+        sealed.m_cost,
+        sealed.t_cost,
+        sealed.p_cost,
+    )?;
+    XChaCha20Poly1305::new(&key.into())
+        .decrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: AAD,
+            },
+        )
+        .map_err(|_| {
+            TwitterError::Auth(
+                "Could not decrypt credentials file. Wrong passphrase, or the file was modified"
+                    .to_string(),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAINTEXT: &[u8] = b"[default]\napi_key = \"xvz1evFS4wEEPTGEFPHBog\"\n";
+
+    #[test]
+    fn round_trip() {
+        let sealed = seal(PLAINTEXT, "correct horse battery staple").unwrap();
+        assert_eq!(
+            open(&sealed, "correct horse battery staple").unwrap(),
+            PLAINTEXT
+        );
+    }
+
+    #[test]
+    fn wrong_passphrase() {
+        let sealed = seal(PLAINTEXT, "correct horse battery staple").unwrap();
+        assert!(matches!(
+            open(&sealed, "hunter2"),
+            Err(TwitterError::Auth(_))
+        ));
+    }
+
+    #[test]
+    fn tampered_ciphertext() {
+        let mut sealed = seal(PLAINTEXT, "correct horse battery staple").unwrap();
+        let mut ciphertext = base64::decode(&sealed.ciphertext).unwrap();
+        ciphertext[0] ^= 1;
+        sealed.ciphertext = base64::encode(ciphertext);
+        assert!(open(&sealed, "correct horse battery staple").is_err());
+    }
+}
@@ -0,0 +1,61 @@
+// Writing something in $VISUAL or $EDITOR, the way `git commit` does
+use super::error::TwitterError;
+use super::private;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+pub fn editor() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| String::from(if cfg!(windows) { "notepad" } else { "vi" }))
+}
+
+// A fresh file in the temp dir, that nobody else can read or guess the name of
+fn draft(text: &str) -> Result<PathBuf, TwitterError> {
+    let mut bytes = [0u8; 8];
+    getrandom::getrandom(&mut bytes).map_err(|err| {
+        TwitterError::Invalid(format!("Could not pick a name for the draft: {}", err))
+    })?;
+    let name = bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    let path = env::temp_dir().join(format!("tw-{}.md", name));
+    private::create(&path, text)?;
+    Ok(path)
+}
+
+// Opens the editor on `text` and returns whatever was saved.
+// The editor is run through the shell, so things like `code --wait` work.
+pub fn edit(text: &str) -> Result<String, TwitterError> {
+    let editor = editor();
+    let path = draft(text)?;
+    let status = if cfg!(windows) {
+        Command::new("cmd")
+            .args(["/C", &format!("{} \"{}\"", editor, path.display())])
+            .status()
+    } else {
+        // The path goes in as $1 so it never needs quoting
+        Command::new("sh")
+            .args(["-c", &format!("{} \"$1\"", editor), "sh"])
+            .arg(&path)
+            .status()
+    };
+    let saved = fs::read_to_string(&path);
+    let _ = fs::remove_file(&path);
+    match status {
+        Ok(status) if status.success() => Ok(saved?),
+        Ok(status) => Err(TwitterError::Invalid(format!(
+            "{} exited with {}",
+            editor, status
+        ))),
+        Err(err) => Err(TwitterError::Invalid(format!(
+            "Could not run {}: {}. Set $VISUAL or $EDITOR to the editor you want",
+            editor, err
+        ))),
+    }
+}
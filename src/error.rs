@@ -1,3 +1,5 @@
+use super::twitter::{TwitterErrorItem, TwitterProblem};
+use reqwest::StatusCode;
 use std::io::Error as IoError;
 use std::{error::Error, fmt};
 use toml::de::Error as TomlDeserializeError;
@@ -12,7 +14,161 @@ pub enum TwitterError {
     MissingArgument(String),
     Invalid(String),
     ProfileNotFound(String),
+    Auth(String),
     Api(String),
+    // Twitter answered, and said no
+    Response(ApiError),
+}
+
+// A decoded error response from the Twitter API
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: u16,
+    pub title: Option<String>,
+    pub problem_type: Option<String>,
+    pub detail: Option<String>,
+    pub errors: Vec<TwitterErrorItem>,
+}
+
+// Coarse buckets so scripts can branch on the exit code (see `TwitterError::exit_code`)
+#[derive(Debug, PartialEq)]
+pub enum ApiErrorKind {
+    BadRequest,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    Duplicate,
+    RateLimited,
+    Server,
+    Unknown,
+}
+
+impl ApiError {
+    // Twitter sends back a JSON problem document for most errors; hang onto as much of it as we can
+    pub fn decode(status: u16, text: &str) -> Self {
+        let problem = serde_json::from_str::<TwitterProblem>(text).unwrap_or_default();
+        let mut error = Self::from_problem(status, problem);
+        if error.title.is_none() && error.detail.is_none() && error.errors.is_empty() {
+            error.title = StatusCode::from_u16(status)
+                .ok()
+                .and_then(|status| status.canonical_reason())
+                .map(String::from);
+        }
+        error
+    }
+
+    // v2 sometimes answers 200 with only `errors` and no `data`. None if that's not what `text` is
+    pub fn decode_errors_only(text: &str) -> Option<Self> {
+        match serde_json::from_str::<TwitterProblem>(text) {
+            Ok(problem) if problem.errors.is_some() => Some(Self::from_problem(200, problem)),
+            _ => None,
+        }
+    }
+
+    fn from_problem(status: u16, problem: TwitterProblem) -> Self {
+        Self {
+            status,
+            title: problem.title,
+            problem_type: problem.r#type,
+            detail: problem.detail,
+            errors: problem.errors.unwrap_or_default(),
+        }
+    }
+
+    pub fn kind(&self) -> ApiErrorKind {
+        // https://developer.twitter.com/en/support/twitter-api/error-troubleshooting
+        let is_duplicate = self.errors.iter().any(|e| e.code == Some(187))
+            || self
+                .detail
+                .as_ref()
+                .map(|detail| detail.contains("duplicate"))
+                .unwrap_or(false);
+        let is_rate_limited = self.errors.iter().any(|e| e.code == Some(88));
+
+        if is_duplicate {
+            ApiErrorKind::Duplicate
+        } else if self.status == 429 || is_rate_limited {
+            ApiErrorKind::RateLimited
+        } else {
+            match self.status {
+                400 => ApiErrorKind::BadRequest,
+                401 => ApiErrorKind::Unauthorized,
+                403 => ApiErrorKind::Forbidden,
+                404 => ApiErrorKind::NotFound,
+                500..=599 => ApiErrorKind::Server,
+                // v2 sometimes returns 200 with only `errors`, e.g. looking up a tweet that doesn't exist
+                _ if self.errors.iter().any(is_not_found) => ApiErrorKind::NotFound,
+                _ => ApiErrorKind::Unknown,
+            }
+        }
+    }
+}
+
+fn is_not_found(item: &TwitterErrorItem) -> bool {
+    item.r#type
+        .as_ref()
+        .map(|t| t.ends_with("resource-not-found"))
+        .unwrap_or(false)
+}
+
+impl fmt::Display for ApiErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            ApiErrorKind::BadRequest => "bad-request",
+            ApiErrorKind::Unauthorized => "unauthorized",
+            ApiErrorKind::Forbidden => "forbidden",
+            ApiErrorKind::NotFound => "not-found",
+            ApiErrorKind::Duplicate => "duplicate",
+            ApiErrorKind::RateLimited => "rate-limited",
+            ApiErrorKind::Server => "server",
+            ApiErrorKind::Unknown => "unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Twitter API error [{}] (HTTP {})",
+            self.kind(),
+            self.status
+        )?;
+        match (&self.title, &self.detail) {
+            (Some(title), Some(detail)) => write!(f, ": {}. {}", title, detail)?,
+            (Some(text), None) | (None, Some(text)) => write!(f, ": {}", text)?,
+            (None, None) => (),
+        };
+        if let Some(ref problem_type) = self.problem_type {
+            if problem_type != "about:blank" {
+                write!(f, "\n  See: {}", problem_type)?;
+            }
+        }
+        for item in self.errors.iter() {
+            write!(f, "\n  - {}", item.summary())?;
+        }
+        Ok(())
+    }
+}
+
+impl TwitterError {
+    // Exit codes are part of the CLI contract, see "Exit codes" in the README
+    pub fn exit_code(&self) -> i32 {
+        match *self {
+            TwitterError::Response(ref err) => match err.kind() {
+                ApiErrorKind::BadRequest => 10,
+                ApiErrorKind::Unauthorized => 11,
+                ApiErrorKind::Forbidden => 12,
+                ApiErrorKind::NotFound => 13,
+                ApiErrorKind::Duplicate => 14,
+                ApiErrorKind::RateLimited => 15,
+                ApiErrorKind::Server => 16,
+                ApiErrorKind::Unknown => 2,
+            },
+            _ => 2,
+        }
+    }
 }
 
 // Allow the use of "{}" format specifier
@@ -27,7 +183,9 @@ impl fmt::Display for TwitterError {
             TwitterError::ProfileNotFound(ref arg) => {
                 write!(f, "Profile not found in credentials file: {}", arg)
             }
+            TwitterError::Auth(ref arg) => write!(f, "Authentication error: {}", arg),
             TwitterError::Api(ref arg) => write!(f, "Twitter API error: {}", arg),
+            TwitterError::Response(ref err) => write!(f, "{}", err),
         }
     }
 }
@@ -75,3 +233,73 @@ impl From<serde_json::Error> for TwitterError {
         TwitterError::Api(message.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exit_code(error: ApiError) -> i32 {
+        TwitterError::Response(error).exit_code()
+    }
+
+    #[test]
+    fn unauthorized() {
+        let error = ApiError::decode(
+            401,
+            r#"{"title":"Unauthorized","type":"about:blank","status":401,"detail":"Unauthorized"}"#,
+        );
+        assert_eq!(error.kind(), ApiErrorKind::Unauthorized);
+        assert_eq!(error.title.as_deref(), Some("Unauthorized"));
+        assert_eq!(exit_code(error), 11);
+        // v1.1 only sends `errors`
+        let error = ApiError::decode(
+            401,
+            r#"{"errors":[{"code":89,"message":"Invalid or expired token."}]}"#,
+        );
+        assert_eq!(error.kind(), ApiErrorKind::Unauthorized);
+        assert_eq!(error.errors[0].code, Some(89));
+    }
+
+    #[test]
+    fn forbidden() {
+        let error = ApiError::decode(
+            403,
+            r#"{"title":"Forbidden","type":"about:blank","status":403,"detail":"Forbidden"}"#,
+        );
+        assert_eq!(error.kind(), ApiErrorKind::Forbidden);
+        assert_eq!(exit_code(error), 12);
+        let error = ApiError::decode(
+            403,
+            r#"{"detail":"You are not allowed to create a Tweet with duplicate content.","type":"about:blank","title":"Forbidden","status":403}"#,
+        );
+        assert_eq!(error.kind(), ApiErrorKind::Duplicate);
+        assert_eq!(exit_code(error), 14);
+    }
+
+    #[test]
+    fn rate_limited() {
+        let error = ApiError::decode(
+            429,
+            r#"{"title":"Too Many Requests","type":"about:blank","status":429,"detail":"Too Many Requests"}"#,
+        );
+        assert_eq!(error.kind(), ApiErrorKind::RateLimited);
+        assert_eq!(exit_code(error), 15);
+        // Not JSON at all, the status still says what happened
+        let error = ApiError::decode(429, "Rate limit exceeded");
+        assert_eq!(error.kind(), ApiErrorKind::RateLimited);
+        assert_eq!(error.title.as_deref(), Some("Too Many Requests"));
+    }
+
+    #[test]
+    fn errors_with_a_200() {
+        let error = ApiError::decode_errors_only(
+            r#"{"errors":[{"value":"20","detail":"Could not find tweet with id: [20].","title":"Not Found Error","resource_type":"tweet","parameter":"id","resource_id":"20","type":"https://api.twitter.com/2/problems/resource-not-found"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(error.status, 200);
+        assert_eq!(error.kind(), ApiErrorKind::NotFound);
+        assert_eq!(exit_code(error), 13);
+        assert!(ApiError::decode_errors_only(r#"{"data":{"id":"20"}}"#).is_none());
+        assert!(ApiError::decode_errors_only("not json").is_none());
+    }
+}
@@ -0,0 +1,200 @@
+// Everything tw posts, one JSON object per line in ~/.twitter_history/<profile>.jsonl.
+// Only ever appended to, so it doubles as an audit trail of what was posted from which account.
+// Use TW_HISTORY_DIR to keep it somewhere else.
+
+use super::error::TwitterError;
+use super::output::HistoryRecord;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+pub fn dir() -> PathBuf {
+    match env::var("TW_HISTORY_DIR") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => {
+            let mut path = home::home_dir().expect("Cannot get your home directory!");
+            path.push(".twitter_history");
+            path
+        }
+    }
+}
+
+// Profile names come from TOML keys, which can be just about anything
+pub fn path(profile: &str) -> PathBuf {
+    let name = profile
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+            _ => '_',
+        })
+        .collect::<String>();
+    dir().join(format!("{}.jsonl", name))
+}
+
+pub fn append(record: &HistoryRecord) -> Result<(), TwitterError> {
+    fs::create_dir_all(dir())?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path(&record.profile))?;
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+    Ok(())
+}
+
+fn read_file(path: &PathBuf) -> Result<Vec<HistoryRecord>, TwitterError> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(TwitterError::Io(err)),
+    };
+    let mut records = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        // One mangled line (say, from a full disk) shouldn't hide the rest of the history
+        match serde_json::from_str::<HistoryRecord>(line) {
+            Ok(record) => records.push(record),
+            Err(err) => eprintln!("Skipping line {} of {}: {}", i + 1, path.display(), err),
+        }
+    }
+    Ok(records)
+}
+
+// Oldest first, the order they were posted in
+pub fn read(profile: &str) -> Result<Vec<HistoryRecord>, TwitterError> {
+    read_file(&path(profile))
+}
+
+// Every profile's history, merged
+pub fn read_all() -> Result<Vec<HistoryRecord>, TwitterError> {
+    let entries = match fs::read_dir(dir()) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(TwitterError::Io(err)),
+    };
+    let mut records = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|extension| extension.to_str()) == Some("jsonl") {
+            records.append(&mut read_file(&path)?);
+        }
+    }
+    records.sort_by(|a, b| a.posted_at.cmp(&b.posted_at));
+    Ok(records)
+}
+
+// Moves a profile's history over when the profile is renamed. The records keep the name they were posted under
+pub fn rename(profile: &str, new_profile: &str) -> Result<(), TwitterError> {
+    let (from, to) = (path(profile), path(new_profile));
+    if from == to || !from.exists() {
+        return Ok(());
+    }
+    if to.exists() {
+        return Err(TwitterError::Invalid(format!(
+            "There's already a history for [{}] at {}",
+            new_profile,
+            to.display()
+        )));
+    }
+    fs::rename(from, to)?;
+    Ok(())
+}
+
+// Newest first, ignoring case
+pub fn search(records: Vec<HistoryRecord>, text: &str) -> Vec<HistoryRecord> {
+    let text = text.to_lowercase();
+    records
+        .into_iter()
+        .rev()
+        .filter(|record| record.text.to_lowercase().contains(&text))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: &str, profile: &str, text: &str, posted_at: &str) -> HistoryRecord {
+        HistoryRecord {
+            id: id.to_string(),
+            url: format!("https://twitter.com/someone/status/{}", id),
+            text: text.to_string(),
+            profile: profile.to_string(),
+            handle: String::from("someone"),
+            posted_at: posted_at.to_string(),
+            in_reply_to_id: None,
+            thread_root_id: id.to_string(),
+        }
+    }
+
+    fn ids(records: &[HistoryRecord]) -> Vec<&str> {
+        records.iter().map(|record| record.id.as_str()).collect()
+    }
+
+    // All in one test, since they share TW_HISTORY_DIR
+    #[test]
+    fn append_read_search_and_rename() {
+        let dir = env::temp_dir().join(format!("tw-test-history-{}", std::process::id()));
+        env::set_var("TW_HISTORY_DIR", &dir);
+        assert!(read("default").unwrap().is_empty());
+        assert!(read_all().unwrap().is_empty());
+
+        append(&record(
+            "1",
+            "default",
+            "Hello world",
+            "2023-01-01T10:00:00Z",
+        ))
+        .unwrap();
+        append(&record("2", "alt/1", "Hello again", "2023-01-01T09:00:00Z")).unwrap();
+        append(&record(
+            "3",
+            "default",
+            "Now available!",
+            "2023-01-01T11:00:00Z",
+        ))
+        .unwrap();
+        assert_eq!(ids(&read("default").unwrap()), vec!["1", "3"]);
+        assert!(dir.join("alt_1.jsonl").exists());
+        assert_eq!(ids(&read("alt/1").unwrap()), vec!["2"]);
+        assert_eq!(ids(&read_all().unwrap()), vec!["2", "1", "3"]);
+
+        // A mangled line is skipped, not the whole file
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(path("default"))
+            .unwrap();
+        writeln!(file, "{{\"id\": \"4\"").unwrap();
+        append(&record(
+            "5",
+            "default",
+            "hello, HELLO",
+            "2023-01-01T12:00:00Z",
+        ))
+        .unwrap();
+        assert_eq!(ids(&read("default").unwrap()), vec!["1", "3", "5"]);
+
+        assert_eq!(
+            ids(&search(read_all().unwrap(), "HELLO")),
+            vec!["5", "1", "2"]
+        );
+        assert_eq!(
+            ids(&search(read("default").unwrap(), "available")),
+            vec!["3"]
+        );
+        assert!(search(read("default").unwrap(), "goodbye").is_empty());
+
+        rename("default", "main").unwrap();
+        assert!(read("default").unwrap().is_empty());
+        assert_eq!(ids(&read("main").unwrap()), vec!["1", "3", "5"]);
+        assert!(rename("main", "alt/1").is_err());
+        assert_eq!(ids(&read("main").unwrap()), vec!["1", "3", "5"]);
+        // Nothing to move
+        rename("nobody", "main").unwrap();
+        assert_eq!(ids(&read("main").unwrap()), vec!["1", "3", "5"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,9 +1,20 @@
 use std::process;
 
 mod args;
+mod cache;
 mod commands;
+mod config;
 mod credentials;
+mod crypto;
+mod editor;
 mod error;
+mod history;
+mod output;
+mod private;
+mod prompt;
+mod stats;
+mod template;
+mod time;
 mod twitter;
 
 // Thanks to WireMock for the idea 😛
@@ -23,14 +34,25 @@ const HELP: &str = "Usage: tw [command] [options]
 
 Commands:
     init
+    login
+    profile [list|show|add|remove|rename|default]
+    auth [invalidate-bearer]
     me
     post [message]
     tweet [message]
+    quote [id] [message]
+    count [message]
     delete [id]
     like [id]
     unlike [id]
+    retweet [id]
+    unretweet [id]
     feed [count]
     home [count]
+    bookmarks [count]
+    stats
+    history [list|show|search]
+    limits [...resources]
     help, -h, --help
     version, -v, --version
 
@@ -39,8 +61,14 @@ You may also ask for help on a specific command:
     tw [command] -h
     tw [command] help
 
+To wait out rate limits and retry transient server errors (up to n times), run:
+    tw [command] --retry n
+
 For enhanced debugging, run:
     tw [command] --debug
+
+To send requests somewhere other than https://api.twitter.com, run:
+    tw [command] --api-base http://localhost:8080
 ";
 
 use args::BaseArgs;
@@ -51,12 +79,23 @@ enum Command {
     Version,
     Me,
     Tweet,
+    Quote,
+    Count,
     Delete,
     Feed,
     Home,
+    Bookmarks,
+    Stats,
+    History,
     Init,
+    Login,
+    Profile,
+    Auth,
     Like,
     Unlike,
+    Retweet,
+    Unretweet,
+    Limits,
 }
 
 fn main() {
@@ -64,7 +103,7 @@ fn main() {
     // https://github.com/BurntSushi/ripgrep/blob/e6cac8b119d0d50646b3ba1aaf53e648c779901a/crates/core/main.rs#L48-L74
     if let Err(err) = BaseArgs::parse().and_then(try_main) {
         eprintln!("{}", err);
-        process::exit(2);
+        process::exit(err.exit_code());
     }
 }
 
@@ -72,12 +111,20 @@ fn try_main(args: BaseArgs) -> Result<(), error::TwitterError> {
     args.debug(&args);
     match command(&args) {
         Command::Tweet => commands::post(&args),
+        Command::Quote => commands::quote(&args),
+        Command::Count => commands::count(&args),
         Command::Delete => commands::delete(&args),
         Command::Feed => commands::feed(&args),
         Command::Home => commands::home(&args),
+        Command::Bookmarks => commands::bookmarks(&args),
+        Command::Stats => commands::stats(&args),
+        Command::History => commands::history(&args),
         Command::Me => commands::me(&args),
         Command::Like => commands::like(&args),
         Command::Unlike => commands::unlike(&args),
+        Command::Retweet => commands::retweet(&args),
+        Command::Unretweet => commands::unretweet(&args),
+        Command::Limits => commands::limits(&args),
         Command::Version => {
             print_banner();
             // Do we have a git hash?
@@ -93,6 +140,9 @@ fn try_main(args: BaseArgs) -> Result<(), error::TwitterError> {
             print_banner();
             commands::init(&args)
         }
+        Command::Login => commands::login(&args),
+        Command::Profile => commands::profile(&args),
+        Command::Auth => commands::auth(&args),
         Command::Help => print_help(),
     }
 }
@@ -103,13 +153,25 @@ fn command(args: &BaseArgs) -> Command {
             "post" => Command::Tweet,
             "p" => Command::Tweet,
             "tweet" => Command::Tweet,
+            "quote" => Command::Quote,
+            "count" => Command::Count,
             "delete" => Command::Delete,
             "like" => Command::Like,
             "unlike" => Command::Unlike,
+            "retweet" => Command::Retweet,
+            "rt" => Command::Retweet,
+            "unretweet" => Command::Unretweet,
             "feed" => Command::Feed,
             "home" => Command::Home,
+            "bookmarks" => Command::Bookmarks,
+            "stats" => Command::Stats,
+            "history" => Command::History,
             "me" => Command::Me,
+            "limits" => Command::Limits,
             "init" => Command::Init,
+            "login" => Command::Login,
+            "profile" => Command::Profile,
+            "auth" => Command::Auth,
             "help" => Command::Help,
             "version" => Command::Version,
             _ => {
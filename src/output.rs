@@ -0,0 +1,399 @@
+// Machine readable output for `--output json|jsonl|csv`, and templated output for `--format`.
+// The records in here are the contract with whatever is on the other end of the pipe, so field names only ever
+// get added, never renamed. They're deliberately separate from the API response structs, which follow Twitter.
+
+use super::args::BaseArgs;
+use super::config;
+use super::error::TwitterError;
+use super::template::Template;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Debug, PartialEq)]
+pub enum Format {
+    Human,
+    Json,
+    Jsonl,
+    Csv,
+    Template(Template),
+}
+
+impl Format {
+    pub fn parse(base_args: &BaseArgs) -> Result<Format, TwitterError> {
+        // --format is either the name of a template in the config file or a template itself
+        if let Some(format) = base_args.get_option::<String>("format", "format") {
+            if base_args.get_option::<String>("output", "o").is_some() {
+                return Err(TwitterError::Invalid(
+                    "Pick one of --format or --output".to_string(),
+                ));
+            }
+            let source = config::template(&format)?.unwrap_or(format);
+            return Ok(Format::Template(Template::parse(&source)?));
+        }
+        match base_args.get_option::<String>("output", "o").as_deref() {
+            None | Some("human") => Ok(Format::Human),
+            Some("json") => Ok(Format::Json),
+            Some("jsonl") => Ok(Format::Jsonl),
+            Some("csv") => Ok(Format::Csv),
+            Some(other) => Err(TwitterError::Invalid(format!(
+                "Unknown output format {}. Try json, jsonl or csv",
+                other
+            ))),
+        }
+    }
+
+    pub fn is_human(&self) -> bool {
+        *self == Format::Human
+    }
+
+    // A list of records: a JSON array, one JSON object per line, or one CSV row each
+    pub fn print<T: Serialize>(&self, records: &[T]) -> Result<(), TwitterError> {
+        let mut stream = self.stream();
+        stream.print(records)?;
+        stream.finish();
+        Ok(())
+    }
+
+    // Same output as `print`, but handed over a batch at a time, e.g. one per page of results
+    pub fn stream(&self) -> Stream<'_> {
+        Stream {
+            format: self,
+            count: 0,
+        }
+    }
+
+    // A single record, e.g. `tw me`. Same as `print`, except JSON gets an object instead of an array of one
+    pub fn print_one<T: Serialize>(&self, record: &T) -> Result<(), TwitterError> {
+        match self {
+            Format::Human => unreachable!("Human output is up to the command"),
+            Format::Json => {
+                println!("{}", serde_json::to_string_pretty(record)?);
+                Ok(())
+            }
+            _ => self.print(std::slice::from_ref(record)),
+        }
+    }
+}
+
+// Nested objects become dotted columns (author.handle, metrics.likes), arrays are written as JSON
+fn flatten(prefix: &str, value: &Value, columns: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                let name = match prefix {
+                    "" => key.to_string(),
+                    prefix => format!("{}.{}", prefix, key),
+                };
+                flatten(&name, value, columns);
+            }
+        }
+        Value::Null => columns.push((prefix.to_string(), String::new())),
+        Value::String(text) => columns.push((prefix.to_string(), text.to_string())),
+        other => columns.push((prefix.to_string(), other.to_string())),
+    }
+}
+
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub struct Stream<'f> {
+    format: &'f Format,
+    count: usize,
+}
+
+impl<'f> Stream<'f> {
+    pub fn print<T: Serialize>(&mut self, records: &[T]) -> Result<(), TwitterError> {
+        print!("{}", self.render(records)?);
+        Ok(())
+    }
+
+    fn render<T: Serialize>(&mut self, records: &[T]) -> Result<String, TwitterError> {
+        let mut out = String::new();
+        for record in records {
+            match self.format {
+                // Commands print their own human friendly output, this only ever sees the machine readable kind
+                Format::Human => unreachable!("Human output is up to the command"),
+                // Written out by hand so the array can be printed as it grows, it ends up identical to
+                // serde_json::to_string_pretty of the whole list
+                Format::Json => {
+                    let pretty = serde_json::to_string_pretty(record)?;
+                    let indented = pretty
+                        .lines()
+                        .map(|line| format!("  {}", line))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    match self.count {
+                        0 => out.push_str(&format!("[\n{}", indented)),
+                        _ => out.push_str(&format!(",\n{}", indented)),
+                    }
+                }
+                Format::Jsonl => out.push_str(&format!("{}\n", serde_json::to_string(record)?)),
+                Format::Csv => {
+                    let mut columns = Vec::new();
+                    flatten("", &serde_json::to_value(record)?, &mut columns);
+                    if self.count == 0 {
+                        let header = columns
+                            .iter()
+                            .map(|(name, _)| escape_csv(name))
+                            .collect::<Vec<_>>();
+                        out.push_str(&format!("{}\n", header.join(",")));
+                    }
+                    let row = columns
+                        .iter()
+                        .map(|(_, value)| escape_csv(value))
+                        .collect::<Vec<_>>();
+                    out.push_str(&format!("{}\n", row.join(",")));
+                }
+                Format::Template(template) => out.push_str(&format!(
+                    "{}\n",
+                    template.render(&serde_json::to_value(record)?)?
+                )),
+            }
+            self.count += 1;
+        }
+        Ok(out)
+    }
+
+    // Closes the JSON array. Nothing to do for the line based formats
+    pub fn finish(self) {
+        print!("{}", self.end());
+    }
+
+    fn end(&self) -> &'static str {
+        match (self.format, self.count) {
+            (Format::Human, _) => unreachable!("Human output is up to the command"),
+            (Format::Json, 0) => "[]\n",
+            (Format::Json, _) => "\n]\n",
+            _ => "",
+        }
+    }
+}
+
+// A tweet from feed, home or bookmarks. Fields the endpoint doesn't return are null rather than missing,
+// so every record has the same shape (and every CSV row the same columns)
+#[derive(Serialize, Debug)]
+pub struct TweetRecord {
+    pub id: String,
+    pub url: String,
+    pub created_at: String, // RFC 3339, e.g. 2021-12-04T23:22:08.000Z
+    pub text: String,
+    pub author: AuthorRecord,
+    pub metrics: MetricsRecord,
+    pub liked: Option<bool>,
+    pub retweeted: Option<bool>,
+    pub in_reply_to_id: Option<String>,
+    pub in_reply_to_handle: Option<String>,
+    pub retweet_of_handle: Option<String>,
+    pub media: Vec<MediaRecord>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct AuthorRecord {
+    pub id: Option<String>,
+    pub handle: Option<String>,
+    pub name: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MetricsRecord {
+    pub replies: Option<usize>,
+    pub retweets: usize,
+    pub quotes: Option<usize>,
+    pub likes: usize,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MediaRecord {
+    pub r#type: String,
+    pub url: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct UserRecord {
+    pub id: String,
+    pub handle: String,
+    pub name: String,
+    pub url: String,
+    pub created_at: String,
+    pub pinned_tweet_id: Option<String>,
+}
+
+// One per tweet posted, so a thread gets a record for every tweet in it
+#[derive(Serialize, Debug)]
+pub struct PostRecord {
+    pub id: String,
+    pub url: String,
+    pub text: String,
+    pub in_reply_to_id: Option<String>,
+}
+
+// A line in ~/.twitter_history/<profile>.jsonl. Doubles as the file format, so the same rules apply to old files
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoryRecord {
+    pub id: String,
+    pub url: String,
+    pub text: String,
+    pub profile: String,
+    pub handle: String,
+    pub posted_at: String, // RFC 3339, when tw posted it
+    pub in_reply_to_id: Option<String>,
+    pub thread_root_id: String, // the first tweet of the thread, or the tweet itself
+}
+
+// `tw count`, one per tweet
+#[derive(Serialize, Debug)]
+pub struct CountRecord {
+    pub text: String,
+    pub length: usize,
+    pub max: usize,
+    pub remaining: i64, // negative when it's too long
+}
+
+#[derive(Serialize, Debug)]
+pub struct DeleteRecord {
+    pub id: String,
+    pub deleted: bool,
+    pub error: Option<String>, // why it wasn't deleted
+}
+
+#[derive(Serialize, Debug)]
+pub struct LikeRecord {
+    pub id: String,
+    pub liked: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RetweetRecord {
+    pub id: String,
+    pub retweeted: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RateLimitRecord {
+    pub resource: String,
+    pub endpoint: String,
+    pub limit: u32,
+    pub remaining: u32,
+    pub reset: u64, // epoch seconds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Serialize)]
+    struct Record {
+        id: String,
+        text: String,
+        author: AuthorRecord,
+        tags: Vec<String>,
+    }
+
+    fn records() -> Vec<Record> {
+        vec![
+            Record {
+                id: String::from("1"),
+                text: String::from("Hello, \"world\"\nsecond line"),
+                author: AuthorRecord {
+                    id: Some(String::from("12")),
+                    handle: Some(String::from("jack")),
+                    name: None,
+                },
+                tags: vec![String::from("a"), String::from("b")],
+            },
+            Record {
+                id: String::from("2"),
+                text: String::from("plain"),
+                author: AuthorRecord {
+                    id: None,
+                    handle: None,
+                    name: Some(String::from("Jack, Jr.")),
+                },
+                tags: Vec::new(),
+            },
+        ]
+    }
+
+    // A stream handed the records one batch at a time, then finished
+    fn output(format: &Format, batches: &[&[Record]]) -> String {
+        let mut stream = format.stream();
+        let mut out = String::new();
+        for batch in batches {
+            out.push_str(&stream.render(batch).unwrap());
+        }
+        out + stream.end()
+    }
+
+    #[test]
+    fn csv_escaping() {
+        assert_eq!(escape_csv("plain"), "plain");
+        assert_eq!(escape_csv("a,b"), "\"a,b\"");
+        assert_eq!(escape_csv("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape_csv("one\ntwo"), "\"one\ntwo\"");
+        assert_eq!(escape_csv("one\r\ntwo"), "\"one\r\ntwo\"");
+        assert_eq!(escape_csv(""), "");
+    }
+
+    #[test]
+    fn flattening() {
+        let mut columns = Vec::new();
+        let value = json!({"id": "1", "author": {"handle": "jack", "name": null, "metrics": {"likes": 3}}, "tags": ["a"], "ok": true});
+        flatten("", &value, &mut columns);
+        let columns = columns
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            columns,
+            vec![
+                "id=1",
+                "author.handle=jack",
+                "author.name=",
+                "author.metrics.likes=3",
+                "tags=[\"a\"]",
+                "ok=true",
+            ]
+        );
+    }
+
+    #[test]
+    fn csv() {
+        let records = records();
+        assert_eq!(
+            output(&Format::Csv, &[&records[..1], &records[1..]]),
+            "id,text,author.id,author.handle,author.name,tags\n\
+             1,\"Hello, \"\"world\"\"\nsecond line\",12,jack,,\"[\"\"a\"\",\"\"b\"\"]\"\n\
+             2,plain,,,\"Jack, Jr.\",[]\n"
+        );
+        assert_eq!(output(&Format::Csv, &[]), "");
+    }
+
+    #[test]
+    fn jsonl() {
+        let records = records();
+        let out = output(&Format::Jsonl, &[&records[..1], &[], &records[1..]]);
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], serde_json::to_string(&records[0]).unwrap());
+        assert_eq!(
+            serde_json::from_str::<Value>(lines[1]).unwrap()["author"]["name"],
+            "Jack, Jr."
+        );
+    }
+
+    #[test]
+    fn json() {
+        let records = records();
+        assert_eq!(
+            output(&Format::Json, &[&records[..1], &records[1..]]),
+            format!("{}\n", serde_json::to_string_pretty(&records).unwrap())
+        );
+        assert_eq!(output(&Format::Json, &[]), "[]\n");
+    }
+}
@@ -0,0 +1,32 @@
+// Files only you can read, for anything holding tokens or secrets. Everything else can use fs::write
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+pub fn write<C: AsRef<[u8]>>(path: &Path, contents: C) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // mode() only applies to new files
+        if path.exists() {
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    options.open(path)?.write_all(contents.as_ref())
+}
+
+// Like `write`, but fails if anything is already at `path`, so a symlink planted there can't send it elsewhere
+pub fn create<C: AsRef<[u8]>>(path: &Path, contents: C) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(contents.as_ref())
+}
@@ -0,0 +1,44 @@
+use super::error::TwitterError;
+use std::io::{self, BufRead, Write};
+
+// Asks a question on stdout and reads a single trimmed line back from stdin
+pub fn ask(question: &str) -> Result<String, TwitterError> {
+    print!("{} ", question);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(answer.trim().to_string())
+}
+
+// A question with a handful of possible answers. Asked on stderr so it works with --output too.
+// Returns the lowercased answer, it's up to the caller to make sense of it
+pub fn choose(question: &str) -> Result<String, TwitterError> {
+    eprint!("{} ", question);
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(answer.trim().to_lowercase())
+}
+
+// A yes/no question, where anything but yes is a no
+pub fn confirm(question: &str) -> Result<bool, TwitterError> {
+    let answer = choose(&format!("{} [y/N]", question))?;
+    Ok(answer == "y" || answer == "yes")
+}
+
+// Same as `ask` but for questions that can't be left blank
+pub fn ask_required(question: &str) -> Result<String, TwitterError> {
+    let answer = ask(question)?;
+    if answer.is_empty() {
+        Err(TwitterError::MissingArgument(
+            question.trim_end_matches(':').to_string(),
+        ))
+    } else {
+        Ok(answer)
+    }
+}
+
+// Like `ask`, but doesn't echo what you type. For passphrases and the like
+pub fn ask_secret(question: &str) -> Result<String, TwitterError> {
+    Ok(rpassword::prompt_password(format!("{} ", question))?)
+}
@@ -0,0 +1,428 @@
+// The numbers behind `tw stats`: totals, averages, best tweets, and when your tweets do well.
+// Everything comes from the public metrics of your own tweets, whether they were just fetched or read back from
+// `tw home --dump` files. Engagement is likes + retweets + replies + quotes.
+
+use super::error::TwitterError;
+use super::time::{civil_from_days, days_from_civil};
+use super::twitter::{TwitterHomeItem, TwitterUser};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+const BAR_WIDTH: usize = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Period {
+    Day,
+    Week,
+    Month,
+}
+
+impl Period {
+    pub fn parse(period: &str) -> Result<Period, TwitterError> {
+        match period {
+            "day" => Ok(Period::Day),
+            "week" => Ok(Period::Week),
+            "month" => Ok(Period::Month),
+            other => Err(TwitterError::Invalid(format!(
+                "Unknown trend period {}. Try day, week or month",
+                other
+            ))),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Period::Day => "day",
+            Period::Week => "week",
+            Period::Month => "month",
+        }
+    }
+}
+
+pub struct Options {
+    pub top: usize,
+    pub period: Period,
+    pub utc_offset: i64, // minutes
+}
+
+// "+02:00", "-5", "+0530". Twitter's timestamps are UTC, this is how far to shift them for the weekday and hour charts
+pub fn parse_utc_offset(offset: &str) -> Result<i64, TwitterError> {
+    let invalid = || {
+        TwitterError::Invalid(format!(
+            "--utc-offset should look like +02:00 or -5, not {}",
+            offset
+        ))
+    };
+    let (sign, rest) = match offset.chars().next() {
+        Some('+') => (1, &offset[1..]),
+        Some('-') => (-1, &offset[1..]),
+        _ => (1, offset),
+    };
+    let digits = rest.replace(':', "");
+    if digits.is_empty() || digits.len() > 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let (hours, minutes) = match digits.len() {
+        1 | 2 => (digits.parse::<i64>().unwrap_or(0), 0),
+        len => (
+            digits[..len - 2].parse::<i64>().unwrap_or(0),
+            digits[len - 2..].parse::<i64>().unwrap_or(0),
+        ),
+    };
+    if hours > 14 || minutes >= 60 {
+        return Err(invalid());
+    }
+    Ok(sign * (hours * 60 + minutes))
+}
+
+fn format_utc_offset(minutes: i64) -> String {
+    let sign = if minutes < 0 { '-' } else { '+' };
+    format!(
+        "{}{:02}:{:02}",
+        sign,
+        minutes.abs() / 60,
+        minutes.abs() % 60
+    )
+}
+
+// When a tweet was posted, in the requested time zone
+#[derive(Debug, PartialEq)]
+struct Moment {
+    days: i64, // since 1970-01-01
+    hour: usize,
+    weekday: usize, // 0 is Monday
+}
+
+impl Moment {
+    // created_at looks like 2021-12-04T23:22:08.000Z
+    fn parse(created_at: &str, utc_offset: i64) -> Option<Moment> {
+        let number = |range: std::ops::Range<usize>| created_at.get(range)?.parse::<i64>().ok();
+        let days = days_from_civil(number(0..4)?, number(5..7)?, number(8..10)?);
+        let minutes = days * 1440 + number(11..13)? * 60 + number(14..16)? + utc_offset;
+        let days = minutes.div_euclid(1440);
+        Some(Moment {
+            days,
+            hour: (minutes.rem_euclid(1440) / 60) as usize,
+            // 1970-01-01 was a Thursday
+            weekday: (days + 3).rem_euclid(7) as usize,
+        })
+    }
+
+    fn period(&self, period: Period) -> String {
+        let (year, month, day) = match period {
+            Period::Week => civil_from_days(self.days - self.weekday as i64),
+            _ => civil_from_days(self.days),
+        };
+        match period {
+            Period::Month => format!("{}-{:02}", year, month),
+            _ => format!("{}-{:02}-{:02}", year, month, day),
+        }
+    }
+}
+
+fn engagement(item: &TwitterHomeItem) -> usize {
+    let metrics = &item.public_metrics;
+    metrics.like_count + metrics.retweet_count + metrics.reply_count + metrics.quote_count
+}
+
+fn average(total: usize, count: usize) -> f64 {
+    match count {
+        0 => 0.0,
+        count => (total as f64 / count as f64 * 100.0).round() / 100.0,
+    }
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct Totals {
+    pub likes: usize,
+    pub retweets: usize,
+    pub replies: usize,
+    pub quotes: usize,
+    pub engagement: usize,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Averages {
+    pub likes: f64,
+    pub retweets: f64,
+    pub replies: f64,
+    pub quotes: f64,
+    pub engagement: f64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TopTweet {
+    pub id: String,
+    pub url: String,
+    pub created_at: String,
+    pub text: String,
+    pub count: usize,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Top {
+    pub likes: Vec<TopTweet>,
+    pub retweets: Vec<TopTweet>,
+    pub replies: Vec<TopTweet>,
+    pub quotes: Vec<TopTweet>,
+}
+
+// Tweets posted on a weekday, in an hour or during a period, and how they did on average
+#[derive(Serialize, Debug, Default)]
+pub struct Bucket {
+    pub key: String,
+    pub tweets: usize,
+    pub engagement: usize,
+    pub average: f64,
+}
+
+impl Bucket {
+    fn add(&mut self, engagement: usize) {
+        self.tweets += 1;
+        self.engagement += engagement;
+        self.average = average(self.engagement, self.tweets);
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct Report {
+    pub tweets: usize,
+    pub first: Option<String>,
+    pub last: Option<String>,
+    pub utc_offset: String,
+    pub totals: Totals,
+    pub averages: Averages,
+    pub top: Top,
+    pub by_weekday: Vec<Bucket>,
+    pub by_hour: Vec<Bucket>,
+    pub trend_period: String,
+    pub trend: Vec<Bucket>,
+}
+
+impl Report {
+    // `author` is only used to build nicer tweet URLs
+    pub fn new(items: &[TwitterHomeItem], author: Option<&TwitterUser>, options: &Options) -> Self {
+        let mut totals = Totals::default();
+        let mut by_weekday = WEEKDAYS
+            .iter()
+            .map(|weekday| Bucket {
+                key: weekday.to_string(),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let mut by_hour = (0..24)
+            .map(|hour| Bucket {
+                key: format!("{:02}", hour),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let mut trend = BTreeMap::<String, Bucket>::new();
+
+        for item in items {
+            let metrics = &item.public_metrics;
+            let engagement = engagement(item);
+            totals.likes += metrics.like_count;
+            totals.retweets += metrics.retweet_count;
+            totals.replies += metrics.reply_count;
+            totals.quotes += metrics.quote_count;
+            totals.engagement += engagement;
+
+            // Anything with a timestamp we can't read still counts towards the totals
+            if let Some(moment) = Moment::parse(&item.created_at, options.utc_offset) {
+                by_weekday[moment.weekday].add(engagement);
+                by_hour[moment.hour].add(engagement);
+                let key = moment.period(options.period);
+                trend
+                    .entry(key.clone())
+                    .or_insert_with(|| Bucket {
+                        key,
+                        ..Default::default()
+                    })
+                    .add(engagement);
+            }
+        }
+
+        let count = items.len();
+        let top = |metric: fn(&TwitterHomeItem) -> usize| {
+            let mut sorted = items.iter().collect::<Vec<_>>();
+            // Newest first for ties
+            sorted.sort_by(|a, b| {
+                metric(b)
+                    .cmp(&metric(a))
+                    .then_with(|| b.created_at.cmp(&a.created_at))
+            });
+            sorted
+                .into_iter()
+                .filter(|item| metric(item) > 0)
+                .take(options.top)
+                .map(|item| TopTweet {
+                    id: item.id.to_string(),
+                    url: item.record(author).url,
+                    created_at: item.created_at.to_string(),
+                    text: item.text.to_string(),
+                    count: metric(item),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        Report {
+            tweets: count,
+            first: items.iter().map(|item| &item.created_at).min().cloned(),
+            last: items.iter().map(|item| &item.created_at).max().cloned(),
+            utc_offset: format_utc_offset(options.utc_offset),
+            averages: Averages {
+                likes: average(totals.likes, count),
+                retweets: average(totals.retweets, count),
+                replies: average(totals.replies, count),
+                quotes: average(totals.quotes, count),
+                engagement: average(totals.engagement, count),
+            },
+            totals,
+            top: Top {
+                likes: top(|item| item.public_metrics.like_count),
+                retweets: top(|item| item.public_metrics.retweet_count),
+                replies: top(|item| item.public_metrics.reply_count),
+                quotes: top(|item| item.public_metrics.quote_count),
+            },
+            by_weekday,
+            by_hour,
+            trend_period: options.period.name().to_string(),
+            trend: trend.into_values().collect(),
+        }
+    }
+
+    pub fn display(&self) {
+        let date = |created_at: &Option<String>| {
+            created_at
+                .as_deref()
+                .map(|created_at| created_at.chars().take(10).collect::<String>())
+                .unwrap_or_default()
+        };
+        if self.tweets == 0 {
+            println!("📊 No tweets to crunch");
+            return;
+        }
+        println!(
+            "📊 {} tweets from {} to {}\n",
+            self.tweets,
+            date(&self.first),
+            date(&self.last)
+        );
+
+        println!("              Total   Per tweet");
+        let rows = [
+            ("Likes", self.totals.likes, self.averages.likes),
+            ("Retweets", self.totals.retweets, self.averages.retweets),
+            ("Replies", self.totals.replies, self.averages.replies),
+            ("Quotes", self.totals.quotes, self.averages.quotes),
+            (
+                "Engagement",
+                self.totals.engagement,
+                self.averages.engagement,
+            ),
+        ];
+        for (name, total, average) in rows {
+            println!("{:<10} {:>8} {:>11.2}", name, total, average);
+        }
+
+        for (name, tweets) in [
+            ("likes", &self.top.likes),
+            ("retweets", &self.top.retweets),
+            ("replies", &self.top.replies),
+            ("quotes", &self.top.quotes),
+        ] {
+            if tweets.is_empty() {
+                continue;
+            }
+            println!("\n🏆 Top {} by {}", tweets.len(), name);
+            for tweet in tweets {
+                let text = tweet.text.lines().next().unwrap_or("");
+                let short = text.chars().take(60).collect::<String>();
+                let ellipsis = if short.len() < tweet.text.len() {
+                    "…"
+                } else {
+                    ""
+                };
+                println!(
+                    "{:>8}  {}  {}{}",
+                    tweet.count,
+                    date(&Some(tweet.created_at.to_string())),
+                    short,
+                    ellipsis
+                );
+                println!("{:>8}  {}", "", tweet.url);
+            }
+        }
+
+        println!(
+            "\n📅 Engagement per tweet by weekday (UTC{})",
+            self.utc_offset
+        );
+        display_buckets(&self.by_weekday);
+        println!("\n🕐 Engagement per tweet by hour (UTC{})", self.utc_offset);
+        display_buckets(&self.by_hour);
+        println!("\n📈 Engagement per tweet by {}", self.trend_period);
+        display_buckets(&self.trend);
+    }
+}
+
+fn display_buckets(buckets: &[Bucket]) {
+    let max = buckets
+        .iter()
+        .map(|bucket| bucket.average)
+        .fold(0.0, f64::max);
+    let width = buckets
+        .iter()
+        .map(|bucket| bucket.key.len())
+        .max()
+        .unwrap_or(0);
+    for bucket in buckets {
+        let bar = match max {
+            max if max > 0.0 => (bucket.average / max * BAR_WIDTH as f64).round() as usize,
+            _ => 0,
+        };
+        println!(
+            "{:<width$}  {:<bar_width$} {:>8.2}  ({} {})",
+            bucket.key,
+            "█".repeat(bar),
+            bucket.average,
+            bucket.tweets,
+            if bucket.tweets == 1 {
+                "tweet"
+            } else {
+                "tweets"
+            },
+            width = width,
+            bar_width = BAR_WIDTH
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moments() {
+        // A Saturday in UTC, but already Sunday in Tokyo
+        let utc = Moment::parse("2021-12-04T23:22:08.000Z", 0).unwrap();
+        assert_eq!((utc.weekday, utc.hour), (5, 23));
+        let tokyo = Moment::parse("2021-12-04T23:22:08.000Z", 9 * 60).unwrap();
+        assert_eq!((tokyo.weekday, tokyo.hour), (6, 8));
+        assert_eq!(tokyo.period(Period::Day), "2021-12-05");
+        assert_eq!(tokyo.period(Period::Week), "2021-11-29");
+        assert_eq!(tokyo.period(Period::Month), "2021-12");
+        assert_eq!(Moment::parse("yesterday", 0), None);
+    }
+
+    #[test]
+    fn utc_offsets() {
+        assert_eq!(parse_utc_offset("+02:00").unwrap(), 120);
+        assert_eq!(parse_utc_offset("-5").unwrap(), -300);
+        assert_eq!(parse_utc_offset("+0530").unwrap(), 330);
+        assert!(parse_utc_offset("+25").is_err());
+        assert!(parse_utc_offset("CET").is_err());
+        assert_eq!(format_utc_offset(-330), "-05:30");
+    }
+}
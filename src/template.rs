@@ -0,0 +1,195 @@
+// The mini template language behind `--format`, e.g. '{author.handle}: {text} ({metrics.likes}❤)'
+//
+//   {field}        a field of the record, see "Machine readable output" in the README for the names.
+//                  Nested fields are separated by dots, list items by their index: {media.0.url}
+//   {{ and }}      literal braces
+//   \n, \t, \\     newline, tab, backslash, because getting a real newline into a shell argument is a pain
+//
+// Missing values (null) render as nothing, lists as their items joined with ", ".
+
+use super::error::TwitterError;
+use serde_json::Value;
+
+#[derive(Debug, PartialEq)]
+enum Part {
+    Literal(String),
+    Field(String),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Template, TwitterError> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = source.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match (c, chars.peek()) {
+                ('{', Some('{')) | ('}', Some('}')) => {
+                    literal.push(c);
+                    chars.next();
+                }
+                ('\\', Some(&next)) if next == 'n' || next == 't' || next == '\\' => {
+                    literal.push(match next {
+                        'n' => '\n',
+                        't' => '\t',
+                        _ => '\\',
+                    });
+                    chars.next();
+                }
+                ('{', _) => {
+                    let mut field = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => field.push(c),
+                            None => {
+                                return Err(TwitterError::Invalid(format!(
+                                    "Template has a {{ without a matching }}: {}",
+                                    source
+                                )))
+                            }
+                        }
+                    }
+                    let field = field.trim().to_string();
+                    if field.is_empty() {
+                        return Err(TwitterError::Invalid(
+                            "Template has an empty {}. Use {{}} for literal braces".to_string(),
+                        ));
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(literal.split_off(0)));
+                    }
+                    parts.push(Part::Field(field));
+                }
+                ('}', _) => {
+                    return Err(TwitterError::Invalid(format!(
+                        "Template has a }} without a matching {{: {}",
+                        source
+                    )))
+                }
+                (c, _) => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Template { parts })
+    }
+
+    pub fn render(&self, record: &Value) -> Result<String, TwitterError> {
+        let mut rendered = String::new();
+        for part in self.parts.iter() {
+            match part {
+                Part::Literal(text) => rendered.push_str(text),
+                Part::Field(path) => match lookup(record, path) {
+                    Some(value) => rendered.push_str(&to_text(value)),
+                    None => {
+                        return Err(TwitterError::Invalid(format!(
+                            "Unknown field {{{}}} in template. Fields: {}",
+                            path,
+                            field_names(record).join(", ")
+                        )))
+                    }
+                },
+            }
+        }
+        Ok(rendered)
+    }
+}
+
+fn lookup<'v>(record: &'v Value, path: &str) -> Option<&'v Value> {
+    path.split('.').try_fold(record, |value, key| match value {
+        Value::Object(fields) => fields.get(key),
+        // Out of range is just an empty value, lists are different lengths for every tweet
+        Value::Array(items) => key
+            .parse::<usize>()
+            .ok()
+            .map(|i| items.get(i).unwrap_or(&Value::Null)),
+        // Anything below a missing value is missing too, e.g. {media.0.url} for a tweet without media
+        Value::Null => Some(&Value::Null),
+        _ => None,
+    })
+}
+
+fn to_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.to_string(),
+        Value::Array(items) => items.iter().map(to_text).collect::<Vec<_>>().join(", "),
+        other => other.to_string(),
+    }
+}
+
+// Every path that can go in a template, for the error message when one can't
+fn field_names(record: &Value) -> Vec<String> {
+    fn collect(prefix: &str, value: &Value, names: &mut Vec<String>) {
+        match value {
+            Value::Object(fields) => {
+                for (key, value) in fields {
+                    let name = match prefix {
+                        "" => key.to_string(),
+                        prefix => format!("{}.{}", prefix, key),
+                    };
+                    collect(&name, value, names);
+                }
+            }
+            _ => names.push(prefix.to_string()),
+        }
+    }
+    let mut names = Vec::new();
+    collect("", record, &mut names);
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn tweet() -> Value {
+        json!({
+            "id": "1",
+            "text": "hello",
+            "author": { "handle": "me", "name": null },
+            "metrics": { "likes": 3 },
+            "media": [{ "type": "photo", "url": "https://pbs.twimg.com/1.jpg" }],
+        })
+    }
+
+    fn render(source: &str) -> String {
+        Template::parse(source).unwrap().render(&tweet()).unwrap()
+    }
+
+    #[test]
+    fn fields() {
+        assert_eq!(
+            render("{author.handle}: {text} ({metrics.likes}❤)"),
+            "me: hello (3❤)"
+        );
+    }
+
+    #[test]
+    fn nulls_and_lists() {
+        assert_eq!(render("[{author.name}]"), "[]");
+        assert_eq!(render("{media.0.url}"), "https://pbs.twimg.com/1.jpg");
+        assert_eq!(render("[{media.1.url}]"), "[]");
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(render("{{{id}}}\\n\\t\\\\"), "{1}\n\t\\");
+    }
+
+    #[test]
+    fn errors() {
+        assert!(Template::parse("{text").is_err());
+        assert!(Template::parse("text}").is_err());
+        assert!(Template::parse("{}").is_err());
+        assert!(Template::parse("{nope}").unwrap().render(&tweet()).is_err());
+    }
+}
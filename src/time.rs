@@ -0,0 +1,177 @@
+// Just enough calendar maths to get by without pulling in a date library
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Days since 1970-01-01, and back again. http://howardhinnant.github.io/date_algorithms.html
+pub fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+pub fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// Now, in the same format Twitter uses for v2 timestamps, e.g. 2021-12-04T23:22:08.000Z
+pub fn now_rfc3339() -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as i64)
+        .unwrap_or(0);
+    let seconds = millis.div_euclid(1000);
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+    let second_of_day = seconds.rem_euclid(86400);
+    format!(
+        "{}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        second_of_day / 3600,
+        second_of_day % 3600 / 60,
+        second_of_day % 60,
+        millis.rem_euclid(1000)
+    )
+}
+
+// An RFC 3339 timestamp with any offset, as UTC to the second: 2023-01-01T14:00:00.5+02:00 is 2023-01-01T12:00:00Z.
+// None if it isn't one, or is missing the offset (there's no telling what it was meant to be relative to)
+pub fn to_utc(timestamp: &str) -> Option<String> {
+    let bytes = timestamp.as_bytes();
+    let separators = [(4, b'-'), (7, b'-'), (10, b'T'), (13, b':'), (16, b':')];
+    if !separators
+        .iter()
+        .all(|(i, separator)| bytes.get(*i) == Some(separator))
+    {
+        return None;
+    }
+    let number = |from: usize, to: usize| -> Option<i64> {
+        let digits = timestamp.get(from..to)?;
+        match digits.chars().all(|c| c.is_ascii_digit()) {
+            true => digits.parse().ok(),
+            false => None,
+        }
+    };
+    let (year, month, day) = (number(0, 4)?, number(5, 7)?, number(8, 10)?);
+    let (hour, minute, second) = (number(11, 13)?, number(14, 16)?, number(17, 19)?);
+    let days = days_from_civil(year, month, day);
+    // 2023-02-30 comes back as 2023-03-02
+    if civil_from_days(days) != (year, month, day) || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+
+    let mut zone = &timestamp[19..];
+    if let Some(fraction) = zone.strip_prefix('.') {
+        let digits = fraction.chars().take_while(|c| c.is_ascii_digit()).count();
+        if digits == 0 {
+            return None;
+        }
+        zone = &fraction[digits..];
+    }
+    let offset_minutes = match zone {
+        "Z" | "z" => 0,
+        // Checked byte by byte before slicing, since anything non-ASCII could split a character
+        _ if zone.len() == 6 && zone.is_ascii() && zone.as_bytes()[3] == b':' => {
+            let sign = match &zone[..1] {
+                "+" => 1,
+                "-" => -1,
+                _ => return None,
+            };
+            let (hours, minutes) = (
+                zone[1..3].parse::<i64>().ok()?,
+                zone[4..].parse::<i64>().ok()?,
+            );
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            sign * (hours * 60 + minutes)
+        }
+        _ => return None,
+    };
+
+    let seconds = days * 86400 + hour * 3600 + minute * 60 + second - offset_minutes * 60;
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+    let second_of_day = seconds.rem_euclid(86400);
+    Some(format!(
+        "{}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        second_of_day / 3600,
+        second_of_day % 3600 / 60,
+        second_of_day % 60
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(civil_from_days(days_from_civil(2024, 2, 29)), (2024, 2, 29));
+        assert_eq!(
+            civil_from_days(days_from_civil(1999, 12, 31) + 1),
+            (2000, 1, 1)
+        );
+    }
+
+    #[test]
+    fn utc() {
+        let utc = |timestamp: &str| to_utc(timestamp);
+        assert_eq!(utc("2023-01-01T12:00:00Z").unwrap(), "2023-01-01T12:00:00Z");
+        assert_eq!(
+            utc("2023-01-01T12:00:00.123Z").unwrap(),
+            "2023-01-01T12:00:00Z"
+        );
+        assert_eq!(
+            utc("2023-01-01T12:00:00+02:00").unwrap(),
+            "2023-01-01T10:00:00Z"
+        );
+        assert_eq!(
+            utc("2023-01-01T01:00:00+02:00").unwrap(),
+            "2022-12-31T23:00:00Z"
+        );
+        assert_eq!(
+            utc("2024-02-28T20:00:00-05:30").unwrap(),
+            "2024-02-29T01:30:00Z"
+        );
+        assert!(utc("2023-01-01T12:00:00").is_none());
+        assert!(utc("2023-01-01 12:00:00Z").is_none());
+        assert!(utc("2023-02-30T12:00:00Z").is_none());
+        assert!(utc("2023-01-01T24:00:00Z").is_none());
+        assert!(utc("2023-01-01T12:00:00+2:00").is_none());
+        assert!(utc("2023-01-01T12:00:00.Z").is_none());
+        assert_eq!(utc("2023-01-01T12:00:00z").unwrap(), "2023-01-01T12:00:00Z");
+        assert_eq!(
+            utc("2023-01-01T12:00:00-00:00").unwrap(),
+            "2023-01-01T12:00:00Z"
+        );
+        assert!(utc("2023-01-01T12:00:00é1:00").is_none());
+        assert!(utc("2023-01-01T12:00:00+0é:0").is_none());
+        assert!(utc("2023-01-01T12:00:00+02-00").is_none());
+        assert!(utc("2023-01-01T12:00:00+24:00").is_none());
+        assert!(utc("2023-01-01T12:00:00+02:60").is_none());
+        assert!(utc("2023-01-01T12:00:00*02:00").is_none());
+        assert!(utc("2023-01-01T12:00:00+02:00:00").is_none());
+        assert!(utc("2023-01-01T12:00:00ZZ").is_none());
+    }
+}
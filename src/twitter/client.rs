@@ -1,26 +1,53 @@
 use super::super::args::BaseArgs;
-use super::super::credentials::Credentials;
-use super::super::error::TwitterError;
+use super::super::cache;
+use super::super::credentials::{self, Credentials, OAuth2Token};
+use super::super::error::{ApiError, TwitterError};
+use super::oauth::{self, Signer};
+use super::oauth2::Pkce;
+use super::HomePages;
+use super::Media;
+use super::MediaUploadResponse;
+use super::OAuth2TokenResponse;
 use super::OauthResponse;
+use super::OauthToken;
 use super::TwitterCreateResponseData;
 use super::TwitterDeleteResponseData;
 use super::TwitterFeed;
 use super::TwitterHomeItem;
 use super::TwitterLikeResponseData;
 use super::TwitterResponse;
+use super::TwitterRetweetResponseData;
 use super::TwitterUser;
+use super::{Auth, NewTweet, Request};
+use super::{RateLimit, RateLimitStatus};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::{Method, StatusCode};
+use serde::de::DeserializeOwned;
 use serde_json::json;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::cell::{Cell, RefCell};
 use std::fs;
-use std::path::PathBuf;
-use urlencoding::encode;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-type ParameterList<'a> = &'a [(&'a str, String)];
+const DEFAULT_API_BASE: &str = "https://api.twitter.com";
+const DEFAULT_WEB_BASE: &str = "https://twitter.com";
+const DEFAULT_UPLOAD_BASE: &str = "https://upload.twitter.com";
+// Twitter takes up to 5MB per APPEND, smaller chunks mean less to send again when one fails
+const MEDIA_CHUNK_BYTES: usize = 1024 * 1024;
 
 pub struct Client<'c> {
     credentials: &'c Credentials,
     client: reqwest::blocking::Client,
     args: &'c BaseArgs,
+    api_base: String,
+    retries: u32,
+    dump_responses: bool,
+    rate_limit: RefCell<Option<RateLimit>>,
+    bearer_token: RefCell<Option<String>>,
+    // Came out of ~/.twitter_cache.toml rather than from Twitter just now, so it might have been invalidated since
+    bearer_token_from_cache: Cell<bool>,
+    oauth2: RefCell<Option<OAuth2Token>>,
+    me: RefCell<Option<TwitterUser>>,
 }
 
 impl<'c> Client<'c> {
@@ -29,87 +56,174 @@ impl<'c> Client<'c> {
         // "For applications wishing to only make a few HTTP requests, the reqwest::blocking API may be more convenient."
         // https://docs.rs/reqwest/0.11.6/reqwest/blocking/index.html
         let client = reqwest::blocking::Client::new();
+        let api_base = api_base(
+            args.get_option::<String>("api-base", "api-base"),
+            std::env::var("TW_API_BASE").ok(),
+            credentials.api_base.clone(),
+        );
+        args.debug(&format!("Using API base: {}", api_base));
+        let retries = args.get("retry", "retry", 0);
+        let dump_responses = args.flags.contains_key("dump");
         Self {
             credentials,
             client,
             args,
+            api_base,
+            retries,
+            dump_responses,
+            rate_limit: RefCell::new(None),
+            bearer_token: RefCell::new(None),
+            bearer_token_from_cache: Cell::new(false),
+            oauth2: RefCell::new(credentials.oauth2.clone()),
+            me: RefCell::new(None),
         }
     }
 
-    // https://developer.twitter.com/en/docs/twitter-api/tweets/manage-tweets/api-reference/post-tweets
-    pub fn post_v2(
+    // Overrides the number of retries given with --retry
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    // Overrides --dump, for commands that write one combined dump instead of one per request
+    pub fn dump_responses(mut self, dump_responses: bool) -> Self {
+        self.dump_responses = dump_responses;
+        self
+    }
+
+    // Pages through `home_v2` following meta.next_token
+    pub fn home_pages(
         &self,
-        message: &str,
-        in_reply_to_tweet_id: &Option<String>,
-    ) -> Result<TwitterCreateResponseData, TwitterError> {
-        self.args.debug(&format!("Posting message: {}", message));
-
-        let base_url = "https://api.twitter.com/2/tweets";
-        let authorization = self.build_authorization("POST", &base_url, None);
-
-        let body = match in_reply_to_tweet_id {
-            Some(id) => json!({
-                "text": message,
-                "reply": {
-                    "in_reply_to_tweet_id": id,
-                }
-            }),
-            None => json!({
-                "text": message,
-            }),
-        };
+        user_id: &str,
+        page_size: i32,
+        start_time: Option<String>,
+        pagination_token: Option<String>,
+    ) -> HomePages<'_, 'c> {
+        HomePages::new(self, user_id, page_size, start_time, pagination_token)
+    }
+
+    // https://developer.twitter.com/en/docs/twitter-api/tweets/manage-tweets/api-reference/post-tweets
+    pub fn post_v2(&self, tweet: &NewTweet) -> Result<TwitterCreateResponseData, TwitterError> {
+        self.args.debug(&format!("Posting message: {}", tweet.text));
+
+        let body = tweet.json();
         self.args.debug(&body);
 
-        // returns Result<Response>
-        // https://docs.rs/reqwest/0.11.6/reqwest/blocking/struct.Response.html
-        let req = self
-            .client
-            .post(base_url)
-            .header("Authorization", authorization)
-            .json(&body);
-        self.args.debug(&req);
+        let request = Request::post("/2/tweets").json(body).name("post");
+        Ok(self.call_v2::<TwitterCreateResponseData>(request)?.data)
+    }
 
-        let res = req.send()?;
-        self.args.debug(&res);
+    // Uploads a picture or video and returns the media id to attach to a tweet.
+    // The file goes up base64 encoded in a form body (`media_data`), which the upload endpoint takes just like
+    // multipart and which gets signed like any other form request.
+    // https://developer.twitter.com/en/docs/twitter-api/v1/media/upload-media/overview
+    pub fn upload_media(&self, media: &Media) -> Result<String, TwitterError> {
+        self.args.debug(&format!("Uploading media: {}", media.path));
 
-        // Possible to use match on the enum if desired
-        // https://docs.rs/reqwest/0.11.6/reqwest/struct.StatusCode.html#impl-1
-        if res.status().is_success() {
-            let text = res.text()?;
-            self.args.debug(&text);
-            let json: TwitterResponse<TwitterCreateResponseData> = serde_json::from_str(&text)?;
-            Ok(json.data)
+        let data = fs::read(&media.path)?;
+        let media_id = if media.is_chunked() {
+            self.upload_media_chunked(media, &data)?
         } else {
-            Err(self.error(res))
+            let request = Request::post("/1.1/media/upload.json")
+                .form("media_data", base64::encode(&data))
+                .form("media_category", media.kind.category())
+                .name("media");
+            self.call::<MediaUploadResponse>(request)?.media_id_string
+        };
+        if let Some(ref alt_text) = media.alt_text {
+            self.media_metadata(&media_id, alt_text)?;
         }
+        Ok(media_id)
     }
 
-    // https://developer.twitter.com/en/docs/twitter-api/tweets/manage-tweets/api-reference/delete-tweets-id
-    pub fn delete_v2(&self, id: &str) -> Result<TwitterDeleteResponseData, TwitterError> {
-        self.args.debug(&format!("Deleting id: {}", id));
+    // INIT, APPEND a chunk at a time, FINALIZE, then wait for Twitter to finish processing it
+    // https://developer.twitter.com/en/docs/twitter-api/v1/media/upload-media/uploading-media/chunked-media-upload
+    fn upload_media_chunked(&self, media: &Media, data: &[u8]) -> Result<String, TwitterError> {
+        let request = Request::post("/1.1/media/upload.json")
+            .form("command", "INIT")
+            .form("total_bytes", data.len())
+            .form("media_type", media.media_type)
+            .form("media_category", media.kind.category())
+            .name("media");
+        let media_id = self.call::<MediaUploadResponse>(request)?.media_id_string;
 
-        let base_url = format!("https://api.twitter.com/2/tweets/{}", id);
-        let authorization = self.build_authorization("DELETE", &base_url, None);
+        let chunks = data.chunks(MEDIA_CHUNK_BYTES).count();
+        for (i, chunk) in data.chunks(MEDIA_CHUNK_BYTES).enumerate() {
+            if chunks > 1 {
+                eprintln!("Uploading {} ({} of {})", media.path, i + 1, chunks);
+            }
+            // APPEND answers with an empty body
+            let request = Request::post("/1.1/media/upload.json")
+                .form("command", "APPEND")
+                .form("media_id", &media_id)
+                .form("segment_index", i)
+                .form("media_data", base64::encode(chunk))
+                .name("media");
+            self.execute(&request)?;
+        }
 
-        // returns Result<Response>
-        // https://docs.rs/reqwest/0.11.6/reqwest/blocking/struct.Response.html
-        let req = self
-            .client
-            .delete(&base_url)
-            .header("Authorization", authorization);
-        self.args.debug(&req);
+        let request = Request::post("/1.1/media/upload.json")
+            .form("command", "FINALIZE")
+            .form("media_id", &media_id)
+            .name("media");
+        let mut response = self.call::<MediaUploadResponse>(request)?;
+        while let Some(info) = response.processing_info {
+            match info.state.as_str() {
+                "succeeded" => break,
+                "failed" => {
+                    let reason = info
+                        .error
+                        .and_then(|error| error.message.or(error.name))
+                        .unwrap_or_else(|| String::from("no reason given"));
+                    return Err(TwitterError::Api(format!(
+                        "Could not process {}: {}",
+                        media.path, reason
+                    )));
+                }
+                _ => {
+                    let wait = info.check_after_secs.unwrap_or(1);
+                    eprintln!(
+                        "Twitter is processing {} ({}%), checking again in {}s",
+                        media.path,
+                        info.progress_percent.unwrap_or(0),
+                        wait
+                    );
+                    thread::sleep(Duration::from_secs(wait));
+                }
+            }
+            let request = Request::get("/1.1/media/upload.json")
+                .query("command", "STATUS")
+                .query("media_id", &media_id)
+                .name("media");
+            response = self.call::<MediaUploadResponse>(request)?;
+        }
+        Ok(media_id)
+    }
+
+    // https://developer.twitter.com/en/docs/twitter-api/v1/media/upload-media/api-reference/post-media-metadata-create
+    fn media_metadata(&self, media_id: &str, alt_text: &str) -> Result<(), TwitterError> {
+        self.args
+            .debug(&format!("Adding alt text to media: {}", media_id));
 
-        let res = req.send()?;
-        self.args.debug(&res);
+        let request = Request::post("/1.1/media/metadata/create.json")
+            .json(json!({
+                "media_id": media_id,
+                "alt_text": {
+                    "text": alt_text,
+                },
+            }))
+            .name("media_metadata");
+        // Another empty body
+        self.execute(&request)?;
+        Ok(())
+    }
 
-        if res.status().is_success() {
-            let text = res.text()?;
-            self.args.debug(&text);
-            let json: TwitterResponse<TwitterDeleteResponseData> = serde_json::from_str(&text)?;
-            Ok(json.data)
-        } else {
-            Err(self.error(res))
-        }
+    // https://developer.twitter.com/en/docs/twitter-api/tweets/manage-tweets/api-reference/delete-tweets-id
+    pub fn delete_v2(&self, id: &str) -> Result<TwitterDeleteResponseData, TwitterError> {
+        self.args.debug(&format!("Deleting id: {}", id));
+
+        let request = Request::delete(&format!("/2/tweets/{}", id)).name("delete");
+        Ok(self.call_v2::<TwitterDeleteResponseData>(request)?.data)
     }
 
     // https://developer.twitter.com/en/docs/twitter-api/v1/tweets/timelines/api-reference/get-statuses-home_timeline
@@ -118,83 +232,93 @@ impl<'c> Client<'c> {
         self.args
             .debug(&format!("Fetching feed with count: {}", count));
 
-        // UGH... to have query params, I will need to send them in too build_authorization separately from the base_url, then re-encode it here...
-        // might need to use a custom struct for feed args, too keep this from getting too wild.
-        let base_url = "https://api.twitter.com/1.1/statuses/home_timeline.json";
-        let params = &[("count", count.to_string())];
-        let authorization = self.build_authorization("GET", base_url, Some(params));
-        let full_url = format!("{}?count={}", base_url, count);
-
-        // returns Result<Response>
-        // https://docs.rs/reqwest/0.11.6/reqwest/blocking/struct.Response.html
-        let req = self
-            .client
-            .get(&full_url)
-            .header("Authorization", authorization);
-        self.args.debug(&req);
-
-        let res = req.send()?;
-        self.args.debug(&res);
+        let request = Request::get("/1.1/statuses/home_timeline.json")
+            .query("count", count)
+            .name("feed");
+        self.call(request)
+    }
 
-        if res.status().is_success() {
-            let text = res.text()?;
-            if self.args.flags.get("dump").is_some() {
-                let mut path = PathBuf::from(home::home_dir().expect("Cannot get your home directory!"));
-                path.push(format!("feed-{}.json", self.timestamp()));
-                fs::write(&path, &text)?;
-                path = fs::canonicalize(&path)?;
-                println!("Dumped data to {}", path.display());
+    // Who the profile is. Most commands need the id before they can do anything, and it hardly ever changes,
+    // so it's cached per profile in ~/.twitter_cache.toml until the profile's tokens change
+    pub fn me(&self) -> Result<TwitterUser, TwitterError> {
+        if let Some(ref me) = *self.me.borrow() {
+            return Ok(me.clone());
+        }
+        let profile = credentials::profile_name(self.args);
+        match cache::get::<TwitterUser>("me", &profile, &self.fingerprint()) {
+            Some(me) => {
+                self.args
+                    .debug(&format!("Using cached user data for {}", profile));
+                self.me.replace(Some(me.clone()));
+                Ok(me)
             }
-            self.args.debug(&text);
-            let json: TwitterFeed = serde_json::from_str(&text)?;
-            Ok(json)
-        } else {
-            Err(self.error(res))
+            None => self.refresh_me(),
         }
     }
 
     // https://developer.twitter.com/en/docs/twitter-api/users/lookup/api-reference/get-users-me
     // This method does not allow Bearer token (Oauth 2) authentication
-    pub fn me(&self) -> Result<TwitterUser, TwitterError> {
-        self.args.debug(&format!("Fetching my user data"));
-
-        let base_url = format!("https://api.twitter.com/2/users/me");
-        let authorization = self.build_authorization(
-            "GET",
-            &base_url,
-            Some(&[
-                ("expansions", "pinned_tweet_id".to_string()),
-                ("user.fields", "created_at".to_string()),
-            ]),
-        );
-        let full_url = format!(
-            "{}?expansions=pinned_tweet_id&user.fields=created_at",
-            base_url
-        );
+    pub fn refresh_me(&self) -> Result<TwitterUser, TwitterError> {
+        self.args.debug(&"Fetching my user data".to_string());
 
-        let req = self
-            .client
-            .get(&full_url)
-            .header("Authorization", authorization);
-        self.args.debug(&req);
+        let request = Request::get("/2/users/me")
+            .query("expansions", "pinned_tweet_id")
+            .query("user.fields", "created_at")
+            .auth(self.user_auth())
+            .name("me");
+        let me = self.call_v2::<TwitterUser>(request)?.data;
+        let profile = credentials::profile_name(self.args);
+        cache::warn(cache::set("me", &profile, &self.fingerprint(), &me));
+        self.me.replace(Some(me.clone()));
+        Ok(me)
+    }
 
-        let res = req.send()?;
-        self.args.debug(&res);
+    // Changes whenever the profile ends up pointing at a different account, or a different server
+    fn fingerprint(&self) -> String {
+        let oauth2 = self.credentials.oauth2.as_ref().map(|token| {
+            token
+                .refresh_token
+                .as_deref()
+                .unwrap_or(&token.access_token)
+        });
+        cache::fingerprint(&[
+            &self.api_base,
+            &self.credentials.api_key,
+            &self.credentials.access_token,
+            oauth2.unwrap_or_default(),
+        ])
+    }
 
-        if res.status().is_success() {
-            let text = res.text()?;
-            if self.args.flags.get("dump").is_some() {
-                let mut path = PathBuf::from(home::home_dir().expect("Cannot get your home directory!"));
-                path.push(format!("me-{}.json", self.timestamp()));
-                fs::write(&path, &text)?;
-                path = fs::canonicalize(&path)?;
-                println!("Dumped data to {}", path.display());
-            }
-            self.args.debug(&text);
-            let json: TwitterResponse<TwitterUser> = serde_json::from_str(&text)?;
-            Ok(json.data)
+    // https://developer.twitter.com/en/docs/twitter-api/users/lookup/api-reference/get-users-id
+    pub fn user_v2(&self, id: &str) -> Result<TwitterUser, TwitterError> {
+        self.args.debug(&format!("Fetching user: {}", id));
+
+        let request = Request::get(&format!("/2/users/{}", id))
+            .query("expansions", "pinned_tweet_id")
+            .query("user.fields", "created_at")
+            .auth(self.user_auth())
+            .name("user");
+        Ok(self.call_v2::<TwitterUser>(request)?.data)
+    }
+
+    // https://developer.twitter.com/en/docs/twitter-api/users/lookup/api-reference/get-users-by-username-username
+    pub fn user_by_username_v2(&self, username: &str) -> Result<TwitterUser, TwitterError> {
+        self.args.debug(&format!("Fetching user: @{}", username));
+
+        let request = Request::get(&format!("/2/users/by/username/{}", username))
+            .query("expansions", "pinned_tweet_id")
+            .query("user.fields", "created_at")
+            .auth(self.user_auth())
+            .name("user");
+        Ok(self.call_v2::<TwitterUser>(request)?.data)
+    }
+
+    // Profiles set up with only `tw login --oauth2` have no OAuth 1.0a keys to sign with
+    fn user_auth(&self) -> Auth {
+        if self.credentials.access_token.is_empty() && self.credentials.oauth2.is_some() {
+            Auth::OAuth2User
         } else {
-            Err(self.error(res))
+            Auth::User
         }
     }
 
@@ -203,37 +327,30 @@ impl<'c> Client<'c> {
         &self,
         user_id: &String,
         count: i32,
-        pagination_token: Option<String>
+        pagination_token: Option<String>,
+        start_time: Option<String>,
     ) -> Result<TwitterResponse<Vec<TwitterHomeItem>>, TwitterError> {
         self.args
             .debug(&format!("Fetching home with count: {}", count));
 
-        let mut base_url = format!("https://api.twitter.com/2/users/{}/tweets?max_results={}&tweet.fields=created_at,author_id,public_metrics", user_id, count);
-        if pagination_token.is_some() {
-            base_url = format!("{}&pagination_token={}", base_url, pagination_token.unwrap());
+        let mut request = Request::get(&format!("/2/users/{}/tweets", user_id))
+            .query("max_results", count)
+            .query("tweet.fields", "created_at,author_id,public_metrics")
+            .auth(Auth::App)
+            .name("home");
+        if let Some(token) = pagination_token {
+            request = request.query("pagination_token", token);
         }
-
-        let req = self.client.get(&base_url).bearer_auth(self.bearer_token()?);
-        self.args.debug(&req);
-
-        let res = req.send()?;
-        self.args.debug(&res);
-
-        if res.status().is_success() {
-            let text = res.text()?;
-            if self.args.flags.get("dump").is_some() {
-                let mut path = PathBuf::from(home::home_dir().expect("Cannot get your home directory!"));
-                path.push(format!("home-{}.json", self.timestamp()));
-                fs::write(&path, &text)?;
-                path = fs::canonicalize(&path)?;
-                println!("Dumped data to {}", path.display());
-            }
-            self.args.debug(&text);
-            let json: TwitterResponse<Vec<TwitterHomeItem>> = serde_json::from_str(&text)?;
-            Ok(json)
-        } else {
-            Err(self.error(res))
+        if let Some(start_time) = start_time {
+            request = request.query("start_time", start_time);
         }
+        // No tweets at all (e.g. nothing since --since) comes back without `data`
+        let response = self.call_v2::<Option<Vec<TwitterHomeItem>>>(request)?;
+        Ok(TwitterResponse {
+            data: response.data.unwrap_or_default(),
+            meta: response.meta,
+            errors: response.errors,
+        })
     }
 
     // https://developer.twitter.com/en/docs/twitter-api/tweets/likes/api-reference/post-users-id-likes
@@ -244,30 +361,12 @@ impl<'c> Client<'c> {
     ) -> Result<TwitterLikeResponseData, TwitterError> {
         self.args.debug(&format!("Liking tweet: {}", tweet_id));
 
-        let base_url = format!("https://api.twitter.com/2/users/{}/likes", user_id);
-        let authorization = self.build_authorization("POST", &base_url, None);
-        let body = json!({
-            "tweet_id": tweet_id,
-        });
-
-        let req = self
-            .client
-            .post(&base_url)
-            .header("Authorization", authorization)
-            .json(&body);
-        self.args.debug(&req);
-
-        let res = req.send()?;
-        self.args.debug(&res);
-
-        if res.status().is_success() {
-            let text = res.text()?;
-            self.args.debug(&text);
-            let json: TwitterResponse<TwitterLikeResponseData> = serde_json::from_str(&text)?;
-            Ok(json.data)
-        } else {
-            Err(self.error(res))
-        }
+        let request = Request::post(&format!("/2/users/{}/likes", user_id))
+            .json(json!({
+                "tweet_id": tweet_id,
+            }))
+            .name("like");
+        Ok(self.call_v2::<TwitterLikeResponseData>(request)?.data)
     }
 
     // https://developer.twitter.com/en/docs/twitter-api/tweets/likes/api-reference/delete-users-id-likes-tweet_id
@@ -276,174 +375,827 @@ impl<'c> Client<'c> {
         user_id: &String,
         tweet_id: &String,
     ) -> Result<TwitterLikeResponseData, TwitterError> {
-        self.args.debug(&format!("Liking tweet: {}", tweet_id));
+        self.args.debug(&format!("Unliking tweet: {}", tweet_id));
 
-        let base_url = format!(
-            "https://api.twitter.com/2/users/{}/likes/{}",
-            user_id, tweet_id
-        );
-        let authorization = self.build_authorization("DELETE", &base_url, None);
+        let request =
+            Request::delete(&format!("/2/users/{}/likes/{}", user_id, tweet_id)).name("unlike");
+        Ok(self.call_v2::<TwitterLikeResponseData>(request)?.data)
+    }
 
-        let req = self
-            .client
-            .delete(&base_url)
-            .header("Authorization", authorization);
-        self.args.debug(&req);
+    // https://developer.twitter.com/en/docs/twitter-api/tweets/retweets/api-reference/post-users-id-retweets
+    pub fn retweet_v2(
+        &self,
+        user_id: &String,
+        tweet_id: &String,
+    ) -> Result<TwitterRetweetResponseData, TwitterError> {
+        self.args.debug(&format!("Retweeting tweet: {}", tweet_id));
 
-        let res = req.send()?;
-        self.args.debug(&res);
+        let request = Request::post(&format!("/2/users/{}/retweets", user_id))
+            .json(json!({
+                "tweet_id": tweet_id,
+            }))
+            .name("retweet");
+        Ok(self.call_v2::<TwitterRetweetResponseData>(request)?.data)
+    }
 
-        if res.status().is_success() {
-            let text = res.text()?;
-            self.args.debug(&text);
-            let json: TwitterResponse<TwitterLikeResponseData> = serde_json::from_str(&text)?;
-            Ok(json.data)
-        } else {
-            Err(self.error(res))
+    // https://developer.twitter.com/en/docs/twitter-api/tweets/retweets/api-reference/delete-users-id-retweets-tweet_id
+    pub fn unretweet_v2(
+        &self,
+        user_id: &String,
+        tweet_id: &String,
+    ) -> Result<TwitterRetweetResponseData, TwitterError> {
+        self.args
+            .debug(&format!("Unretweeting tweet: {}", tweet_id));
+
+        let request = Request::delete(&format!("/2/users/{}/retweets/{}", user_id, tweet_id))
+            .name("unretweet");
+        Ok(self.call_v2::<TwitterRetweetResponseData>(request)?.data)
+    }
+
+    // https://developer.twitter.com/en/docs/twitter-api/v1/developer-utilities/rate-limit-status/api-reference/get-application-rate_limit_status
+    pub fn rate_limit_status(&self, resources: &[String]) -> Result<RateLimitStatus, TwitterError> {
+        self.args.debug(&"Fetching rate limit status".to_string());
+
+        let mut request = Request::get("/1.1/application/rate_limit_status.json").name("limits");
+        if !resources.is_empty() {
+            request = request.query("resources", resources.join(","));
+        }
+        self.call(request)
+    }
+
+    // Step 1 of PIN-based OAuth: get a temporary request token
+    // https://developer.twitter.com/en/docs/authentication/api-reference/request_token
+    pub fn request_token(&self) -> Result<OauthToken, TwitterError> {
+        self.args.debug(&"Fetching OAuth request token".to_string());
+
+        let request = Request::post("/oauth/request_token")
+            .auth(Auth::Login {
+                token: None,
+                token_secret: String::new(),
+                oauth_params: vec![("oauth_callback".to_string(), "oob".to_string())],
+            })
+            .name("request_token");
+        self.call_form(request)
+    }
+
+    // Step 2 of PIN-based OAuth: the URL the user visits to approve the app and get a PIN
+    // https://developer.twitter.com/en/docs/authentication/api-reference/authorize
+    pub fn authorize_url(&self, request_token: &OauthToken) -> String {
+        format!(
+            "{}?oauth_token={}",
+            self.url("/oauth/authorize"),
+            oauth::percent_encode(&request_token.oauth_token)
+        )
+    }
+
+    // Step 3 of PIN-based OAuth: trade the request token and PIN for a long-lived access token
+    // https://developer.twitter.com/en/docs/authentication/api-reference/access_token
+    pub fn access_token(
+        &self,
+        request_token: &OauthToken,
+        pin: &str,
+    ) -> Result<OauthToken, TwitterError> {
+        self.args.debug(&"Fetching OAuth access token".to_string());
+
+        let request = Request::post("/oauth/access_token")
+            .auth(Auth::Login {
+                token: Some(request_token.oauth_token.to_string()),
+                token_secret: request_token.oauth_token_secret.to_string(),
+                oauth_params: vec![("oauth_verifier".to_string(), pin.to_string())],
+            })
+            .name("access_token");
+        self.call_form(request)
+    }
+
+    // https://developer.twitter.com/en/docs/twitter-api/tweets/bookmarks/api-reference/get-users-id-bookmarks
+    // Only works with an OAuth 2.0 user token with the bookmark.read scope
+    pub fn bookmarks_v2(
+        &self,
+        user_id: &String,
+        count: i32,
+        pagination_token: Option<String>,
+    ) -> Result<TwitterResponse<Vec<TwitterHomeItem>>, TwitterError> {
+        self.args
+            .debug(&format!("Fetching bookmarks with count: {}", count));
+
+        let mut request = Request::get(&format!("/2/users/{}/bookmarks", user_id))
+            .query("max_results", count)
+            .query("tweet.fields", "created_at,author_id,public_metrics")
+            .auth(Auth::OAuth2User)
+            .name("bookmarks");
+        if let Some(token) = pagination_token {
+            request = request.query("pagination_token", token);
+        }
+        self.call_v2(request)
+    }
+
+    // Where to send the user to approve the app
+    // https://developer.twitter.com/en/docs/authentication/oauth-2-0/user-access-token
+    pub fn oauth2_authorize_url(&self, pkce: &Pkce, redirect_uri: &str, scopes: &str) -> String {
+        let params = [
+            ("response_type", "code"),
+            (
+                "client_id",
+                self.credentials.client_id.as_deref().unwrap_or(""),
+            ),
+            ("redirect_uri", redirect_uri),
+            ("scope", scopes),
+            ("state", &pkce.state),
+            ("code_challenge", &pkce.challenge),
+            ("code_challenge_method", "S256"),
+        ];
+        let query = params
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, oauth::percent_encode(value)))
+            .collect::<Vec<String>>()
+            .join("&");
+        format!("{}?{}", self.web_url("/i/oauth2/authorize"), query)
+    }
+
+    // Trades the authorization code from the callback for an access token (and refresh token)
+    pub fn oauth2_exchange_code(
+        &self,
+        code: &str,
+        pkce: &Pkce,
+        redirect_uri: &str,
+    ) -> Result<OAuth2Token, TwitterError> {
+        self.args
+            .debug(&"Exchanging OAuth 2.0 authorization code".to_string());
+
+        let request = Request::post("/2/oauth2/token")
+            .form("code", code)
+            .form("grant_type", "authorization_code")
+            .form(
+                "client_id",
+                self.credentials.client_id.as_deref().unwrap_or(""),
+            )
+            .form("redirect_uri", redirect_uri)
+            .form("code_verifier", &pkce.verifier)
+            .auth(Auth::OAuth2Client)
+            .name("oauth2_token");
+        let json: OAuth2TokenResponse = self.call(request)?;
+        Ok(json.into())
+    }
+
+    fn oauth2_refresh(&self, refresh_token: &str) -> Result<OAuth2Token, TwitterError> {
+        self.args
+            .debug(&"Refreshing OAuth 2.0 access token".to_string());
+
+        let request = Request::post("/2/oauth2/token")
+            .form("refresh_token", refresh_token)
+            .form("grant_type", "refresh_token")
+            .form(
+                "client_id",
+                self.credentials.client_id.as_deref().unwrap_or(""),
+            )
+            .auth(Auth::OAuth2Client)
+            .name("oauth2_token");
+        let json: OAuth2TokenResponse = self.call(request)?;
+        Ok(json.into())
+    }
+
+    // Hands out the profile's OAuth 2.0 access token, refreshing (and saving) it first if it has expired
+    fn oauth2_access_token(&self) -> Result<String, TwitterError> {
+        let token = match *self.oauth2.borrow() {
+            Some(ref token) => token.clone(),
+            None => {
+                return Err(TwitterError::Auth(
+                    "This command needs an OAuth 2.0 user token. Run `tw login --oauth2` first"
+                        .to_string(),
+                ))
+            }
+        };
+        // A minute of slack so the token doesn't expire mid-request
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        if token.expires_at > now + 60 {
+            return Ok(token.access_token);
+        }
+
+        let refresh_token = token.refresh_token.ok_or_else(|| {
+            TwitterError::Auth(
+                "OAuth 2.0 token has expired and there is no refresh token. Run `tw login --oauth2` again"
+                    .to_string(),
+            )
+        })?;
+        let mut refreshed = self.oauth2_refresh(&refresh_token)?;
+        // Twitter doesn't always send a new refresh token
+        if refreshed.refresh_token.is_none() {
+            refreshed.refresh_token = Some(refresh_token);
+        }
+        credentials::write_profile_value(
+            &credentials::path(self.args),
+            &credentials::profile_name(self.args),
+            "oauth2",
+            toml::Value::try_from(&refreshed)?,
+        )?;
+        self.oauth2.replace(Some(refreshed.clone()));
+        Ok(refreshed.access_token)
+    }
+
+    // The most recent rate limit headers we've seen from Twitter, if any
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.rate_limit.borrow().clone()
+    }
+
+    // Sleeps until the window resets when the last response said there are no requests left, instead of
+    // sending one we know will be a 429. Limits are per endpoint, so only use this for runs of the same request
+    pub fn wait_for_rate_limit(&self, next: &str) {
+        if let Some(rate_limit) = self.rate_limit() {
+            if rate_limit.remaining == 0 {
+                eprintln!(
+                    "Rate limited, waiting {}s for the next {}",
+                    rate_limit.wait().as_secs(),
+                    next
+                );
+                thread::sleep(rate_limit.wait());
+            }
         }
     }
 
     // https://developer.twitter.com/en/docs/authentication/api-reference/token
+    // App-only tokens are good until they're invalidated, so they're cached per API key in ~/.twitter_cache.toml
+    // and only fetched when there isn't one yet
     fn bearer_token(&self) -> Result<String, TwitterError> {
-        self.args.debug(&format!("Fetching Oauth Bearer token"));
+        if let Some(ref token) = *self.bearer_token.borrow() {
+            return Ok(token.to_string());
+        }
+        let (key, fingerprint) = self.bearer_cache_entry();
+        if let Some(json) = cache::get::<OauthResponse>("bearer", &key, &fingerprint) {
+            self.args
+                .debug(&"Using cached Oauth Bearer token".to_string());
+            self.bearer_token_from_cache.set(true);
+            self.bearer_token.replace(Some(json.access_token.clone()));
+            return Ok(json.access_token);
+        }
+        self.args.debug(&"Fetching Oauth Bearer token".to_string());
+
+        let request = Request::post("/oauth2/token")
+            .query("grant_type", "client_credentials")
+            .auth(Auth::Basic)
+            .name("token");
+        let json: OauthResponse = self.call(request)?;
+        cache::warn(cache::set("bearer", &key, &fingerprint, &json));
+        self.bearer_token_from_cache.set(false);
+        self.bearer_token.replace(Some(json.access_token.clone()));
+        Ok(json.access_token)
+    }
+
+    // https://developer.twitter.com/en/docs/authentication/api-reference/invalidate_bearer_token
+    // For when a token may have leaked, or before rotating the API key. Returns the token that was invalidated
+    pub fn invalidate_bearer_token(&self) -> Result<String, TwitterError> {
+        let token = self.bearer_token()?;
+        self.args
+            .debug(&"Invalidating Oauth Bearer token".to_string());
+
+        // In the body rather than the query string, so --debug doesn't print it with the URL
+        let request = Request::post("/oauth2/invalidate_token")
+            .form("access_token", &token)
+            // Has to be signed with OAuth 1.0a, a bearer token can't be used to do away with itself
+            .auth(Auth::User)
+            .name("invalidate_token");
+        let result = self.call::<OauthResponse>(request);
+        // Whether or not Twitter took it, it's no use keeping around
+        self.forget_bearer_token()?;
+        Ok(result?.access_token)
+    }
 
-        let base_url = "https://api.twitter.com/oauth2/token?grant_type=client_credentials";
-        let req = self.client.post(base_url).basic_auth(
+    fn forget_bearer_token(&self) -> Result<(), TwitterError> {
+        self.bearer_token.replace(None);
+        self.bearer_token_from_cache.set(false);
+        cache::remove("bearer", &self.bearer_cache_entry().0)
+    }
+
+    // Keyed by the API key (hashed, the cache isn't the place for keys), and dropped when the secret changes
+    fn bearer_cache_entry(&self) -> (String, String) {
+        let key = cache::fingerprint(&[&self.api_base, &self.credentials.api_key]);
+        let fingerprint = cache::fingerprint(&[
+            &self.api_base,
             &self.credentials.api_key,
-            Some(&self.credentials.api_key_secret),
-        );
-        self.args.debug(&req);
-        let res = req.send()?;
-        self.args.debug(&res);
+            &self.credentials.api_key_secret,
+        ]);
+        (key, fingerprint)
+    }
+
+    // Sends a request and deserializes the v2 `{ data, meta, errors }` envelope
+    pub fn call_v2<T: DeserializeOwned>(
+        &self,
+        request: Request,
+    ) -> Result<TwitterResponse<T>, TwitterError> {
+        let text = self.execute(&request)?;
+        self.parse(&text)
+    }
+
+    // Sends a request and deserializes the body as-is (v1.1 endpoints don't have an envelope)
+    pub fn call<T: DeserializeOwned>(&self, request: Request) -> Result<T, TwitterError> {
+        let text = self.execute(&request)?;
+        let json: T = serde_json::from_str(&text)?;
+        Ok(json)
+    }
 
+    // Sends a request whose response is form encoded rather than JSON (looking at you, oauth/*)
+    fn call_form<T: DeserializeOwned>(&self, request: Request) -> Result<T, TwitterError> {
+        let text = self.execute(&request)?;
+        let fields = oauth::parse_query(&text)
+            .into_iter()
+            .map(|(key, value)| (key, serde_json::Value::String(value)))
+            .collect::<serde_json::Map<String, serde_json::Value>>();
+        let json: T = serde_json::from_value(serde_json::Value::Object(fields))?;
+        Ok(json)
+    }
+
+    fn execute(&self, request: &Request) -> Result<String, TwitterError> {
+        match self.execute_once(request) {
+            // A cached bearer token stops working when it's invalidated or the app's keys are regenerated.
+            // Twitter only says so with a 401, so fetch a fresh one and try again, once
+            Err(TwitterError::Response(ref error))
+                if error.status == 401
+                    && matches!(request.auth, Auth::App)
+                    && self.bearer_token_from_cache.get() =>
+            {
+                self.args.debug(
+                    &"Cached Oauth Bearer token was rejected, fetching a new one".to_string(),
+                );
+                self.forget_bearer_token()?;
+                self.execute_once(request)
+            }
+            result => result,
+        }
+    }
+
+    // The one place where requests get signed, sent, checked, and dumped
+    fn execute_once(&self, request: &Request) -> Result<String, TwitterError> {
+        let base_url = match request.path.starts_with("/1.1/media/") {
+            true => self.upload_url(&request.path),
+            false => self.url(&request.path),
+        };
+        let full_url = if request.query.is_empty() {
+            base_url.clone()
+        } else {
+            let query = request
+                .query
+                .iter()
+                .map(|(key, value)| {
+                    format!(
+                        "{}={}",
+                        oauth::percent_encode(key),
+                        oauth::percent_encode(value)
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join("&");
+            format!("{}?{}", base_url, query)
+        };
+        let bearer_token = match request.auth {
+            Auth::App => Some(self.bearer_token()?),
+            Auth::OAuth2User => Some(self.oauth2_access_token()?),
+            _ => None,
+        };
+
+        // returns Result<Response>
+        // https://docs.rs/reqwest/0.11.6/reqwest/blocking/struct.Response.html
+        let res = self.send(&request.method, || {
+            let mut req = self.client.request(request.method.clone(), &full_url);
+            req = match request.auth {
+                Auth::User => {
+                    // The query string and form params get signed along with the oauth_* params; JSON bodies don't
+                    let authorization = self.signer().authorization(
+                        request.method.as_str(),
+                        &full_url,
+                        &request.form,
+                    );
+                    req.headers(sensitive(authorization))
+                }
+                Auth::App | Auth::OAuth2User => req.bearer_auth(bearer_token.as_ref().unwrap()),
+                Auth::OAuth2Client => match self.credentials.client_secret {
+                    Some(ref client_secret) => req.basic_auth(
+                        self.credentials.client_id.as_deref().unwrap_or(""),
+                        Some(client_secret),
+                    ),
+                    None => req,
+                },
+                Auth::Basic => req.basic_auth(
+                    &self.credentials.api_key,
+                    Some(&self.credentials.api_key_secret),
+                ),
+                Auth::Login {
+                    ref token,
+                    ref token_secret,
+                    ref oauth_params,
+                } => {
+                    let signer = oauth_params.iter().fold(
+                        Signer::new(
+                            &self.credentials.api_key,
+                            &self.credentials.api_key_secret,
+                            token.as_deref(),
+                            token_secret,
+                        ),
+                        |signer, (key, value)| signer.with_oauth_param(key, value),
+                    );
+                    let authorization =
+                        signer.authorization(request.method.as_str(), &full_url, &[]);
+                    req.headers(sensitive(authorization))
+                }
+            };
+            match request.body {
+                Some(ref body) => req.json(body),
+                None if !request.form.is_empty() => req.form(&request.form),
+                None => req,
+            }
+        })?;
+
+        // Possible to use match on the enum if desired
+        // https://docs.rs/reqwest/0.11.6/reqwest/struct.StatusCode.html#impl-1
         if res.status().is_success() {
             let text = res.text()?;
-            self.args.debug(&text);
-            let json: OauthResponse = serde_json::from_str(&text)?;
-            Ok(json.access_token)
+            // Token responses contain secrets, those never get printed or written to disk
+            let is_token_request = matches!(
+                request.auth,
+                Auth::Basic | Auth::Login { .. } | Auth::OAuth2Client
+            ) || request.path == "/oauth2/invalidate_token";
+            if !is_token_request {
+                self.args.debug(&text);
+            }
+            if self.dump_responses && !is_token_request {
+                self.dump(&request.name, &text)?;
+            }
+            Ok(text)
         } else {
             Err(self.error(res))
         }
     }
 
-    // Twitter takes their authorization seriously
-    // https://developer.twitter.com/en/docs/authentication/oauth-1-0a/creating-a-signature
-    fn build_authorization(
+    // Writes `text` to ~/<name>-<millis>.json
+    pub fn dump(&self, name: &str, text: &str) -> Result<(), TwitterError> {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis())
+            .unwrap_or(0);
+        let mut path = home::home_dir().expect("Cannot get your home directory!");
+        path.push(format!("{}-{}.json", name, millis));
+        fs::write(&path, text)?;
+        path = fs::canonicalize(&path)?;
+        eprintln!("Dumped data to {}", path.display());
+        Ok(())
+    }
+
+    // Sends the request built by `build`, retrying on 429s and transient 5xx errors when `--retry <n>` is given.
+    // The request is rebuilt for every attempt so that OAuth timestamps and nonces stay fresh.
+    // A 5xx can come back after Twitter did the work anyway, so POSTs only retry on 429s, or a flaky
+    // moment while posting a thread would post the same tweet twice
+    fn send<F>(
         &self,
-        method: &str,
-        base_url: &str,
-        request_params: Option<ParameterList>,
-    ) -> String {
-        let parameters = &[
-            ("oauth_consumer_key", self.credentials.api_key.to_owned()),
-            ("oauth_nonce", self.nonce()),
-            ("oauth_signature_method", "HMAC-SHA1".to_string()),
-            ("oauth_timestamp", self.timestamp()),
-            ("oauth_token", self.credentials.access_token.to_owned()),
-            ("oauth_version", "1.0".to_string()),
-        ];
-        let encoded_request = format!(
-            "{}&{}&{}",
-            method,
-            encode(base_url),
-            encode(&self.parameter_string(parameters, request_params, "&", false))
-        );
-        let hashed_request = self.hash(&self.signing_key(), &encoded_request);
-        let oath_signature = base64::encode(&hashed_request);
+        method: &Method,
+        build: F,
+    ) -> Result<reqwest::blocking::Response, TwitterError>
+    where
+        F: Fn() -> reqwest::blocking::RequestBuilder,
+    {
+        let mut attempt = 0;
+        loop {
+            let req = build();
+            self.args.debug(&req);
+            let res = req.send()?;
+            self.args.debug(&res);
+
+            let rate_limit = RateLimit::from_headers(res.headers());
+            if let Some(ref rate_limit) = rate_limit {
+                self.args.debug(rate_limit);
+                self.rate_limit.replace(Some(rate_limit.clone()));
+            }
+
+            let status = res.status();
+            if !should_retry(method, status, attempt, self.retries) {
+                return Ok(res);
+            }
 
-        let authorization = self.authorization_header(parameters, &oath_signature);
-        authorization
+            // Rate limits tell us exactly when they reset; anything else gets exponential backoff
+            let wait = match rate_limit {
+                Some(ref rate_limit) if status == StatusCode::TOO_MANY_REQUESTS => {
+                    rate_limit.wait()
+                }
+                _ => backoff(attempt),
+            };
+            attempt += 1;
+            eprintln!(
+                "{} from Twitter, retrying in {}s (attempt {} of {})",
+                status,
+                wait.as_secs(),
+                attempt,
+                self.retries
+            );
+            thread::sleep(wait);
+        }
     }
 
-    // This is obviously a very fake nonce but it should be fine I think
-    fn nonce(&self) -> String {
-        let timestamp = self.timestamp();
-        // is there really no easier way to write a byte slice to hex string?
-        self.hash(&timestamp, &timestamp)
-            .iter()
-            .fold(String::new(), |mut string, &byte| {
-                string.push_str(&format!("{:02X}", byte));
-                string
-            })
+    fn signer(&self) -> Signer<'_> {
+        Signer::new(
+            &self.credentials.api_key,
+            &self.credentials.api_key_secret,
+            Some(&self.credentials.access_token),
+            &self.credentials.access_token_secret,
+        )
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.api_base, path)
     }
 
-    fn timestamp(&self) -> String {
-        match SystemTime::now().duration_since(UNIX_EPOCH) {
-            Ok(duration) => duration.as_secs().to_string(),
-            // truly no idea what would cause this
-            Err(err) => panic!("{}", err),
+    // Pages meant for a browser live on twitter.com rather than api.twitter.com
+    fn web_url(&self, path: &str) -> String {
+        if self.api_base == DEFAULT_API_BASE {
+            format!("{}{}", DEFAULT_WEB_BASE, path)
+        } else {
+            self.url(path)
         }
     }
 
-    fn hash(&self, key: &str, input: &str) -> [u8; 20] {
-        // This lib looks a little jank but it works and its tiny https://docs.rs/hmac-sha1/0.1.3/hmacsha1/
-        hmacsha1::hmac_sha1(key.as_bytes(), input.as_bytes())
+    // Media uploads have a host of their own
+    fn upload_url(&self, path: &str) -> String {
+        if self.api_base == DEFAULT_API_BASE {
+            format!("{}{}", DEFAULT_UPLOAD_BASE, path)
+        } else {
+            self.url(path)
+        }
     }
 
-    fn signing_key(&self) -> String {
-        format!(
-            "{}&{}",
-            encode(&self.credentials.api_key_secret),
-            encode(&self.credentials.access_token_secret),
-        )
+    // v2 responses can carry `errors` next to `data` (partial success), or only `errors` with a 200 status
+    fn parse<T: DeserializeOwned>(&self, text: &str) -> Result<TwitterResponse<T>, TwitterError> {
+        match serde_json::from_str::<TwitterResponse<T>>(text) {
+            Ok(json) => {
+                if let Some(ref errors) = json.errors {
+                    for item in errors {
+                        eprintln!("Warning: {}", item.summary());
+                    }
+                }
+                Ok(json)
+            }
+            Err(err) => match ApiError::decode_errors_only(text) {
+                Some(error) => Err(TwitterError::Response(error)),
+                None => Err(TwitterError::from(err)),
+            },
+        }
     }
 
-    // https://developer.twitter.com/en/docs/authentication/oauth-1-0a/authorizing-a-request
-    fn authorization_header(&self, params: ParameterList, signature: &str) -> String {
-        format!(
-            "Oauth {}",
-            self.parameter_string(
-                params,
-                Some(&[("oauth_signature", signature.to_string())]),
-                ",",
-                true
-            )
-        )
+    fn error(&self, res: reqwest::blocking::Response) -> TwitterError {
+        let status = res.status();
+        let text = res.text().unwrap_or_default();
+        self.args.debug(&text);
+        TwitterError::Response(ApiError::decode(status.as_u16(), &text))
     }
+}
 
-    /// Takes a list of parameters and returns a string of them all joined by the given separator.
-    /// All parameters are URL encoded.
-    ///
-    /// Example
-    ///    self.parameter_string(&[("test", "data".to_string()), ("is", "fun".to_string())], "&", false)
-    ///    => "test=data&is=fun"
-    ///
-    ///    self.parameter_string(&[("test", "data".to_string()), ("is", "fun".to_string())], ",", true)
-    ///    => "test=\"data\",is=\"fun\""
-    fn parameter_string(
-        &self,
-        oauth_params: ParameterList,
-        request_params: Option<ParameterList>,
-        join_str: &str,
-        wrap_in_quotes: bool,
-    ) -> String {
-        let mut params = match request_params {
-            Some(params) => [params, oauth_params].concat(),
-            None => [oauth_params].concat(),
-        };
-        params.sort_by(|a, b| a.0.cmp(b.0));
-        params
-            .iter()
-            .map(|(key, value)| {
-                if wrap_in_quotes {
-                    format!("{}=\"{}\"", encode(key), encode(value))
-                } else {
-                    format!("{}={}", encode(key), encode(value))
+// Marked sensitive so --debug prints "Sensitive" instead of the access token in the header.
+// Goes through a HeaderMap because `RequestBuilder::header` clears the flag again.
+fn sensitive(authorization: String) -> HeaderMap {
+    let mut value =
+        HeaderValue::from_str(&authorization).expect("OAuth headers are always percent encoded");
+    value.set_sensitive(true);
+    let mut headers = HeaderMap::new();
+    headers.insert(AUTHORIZATION, value);
+    headers
+}
+
+// Whether a response is worth another go, after `attempt` retries out of the `retries` allowed.
+// 429s always are, transient 5xx errors only for requests that are safe to repeat
+fn should_retry(method: &Method, status: StatusCode, attempt: u32, retries: u32) -> bool {
+    let is_rate_limited = status == StatusCode::TOO_MANY_REQUESTS;
+    let is_idempotent = matches!(*method, Method::GET | Method::DELETE);
+    let is_transient = is_idempotent
+        && matches!(
+            status,
+            StatusCode::INTERNAL_SERVER_ERROR
+                | StatusCode::BAD_GATEWAY
+                | StatusCode::SERVICE_UNAVAILABLE
+                | StatusCode::GATEWAY_TIMEOUT
+        );
+    (is_rate_limited || is_transient) && attempt < retries
+}
+
+// 1s, 2s, 4s, ... capped at a minute, plus up to a second of jitter so parallel invocations don't stampede
+fn backoff(attempt: u32) -> Duration {
+    let seconds = 2u64.saturating_pow(attempt).min(60);
+    let mut bytes = [0u8; 2];
+    // No jitter is still a perfectly good backoff
+    let jitter = match getrandom::getrandom(&mut bytes) {
+        Ok(()) => u16::from_le_bytes(bytes) % 1000,
+        Err(_) => 0,
+    };
+    Duration::from_secs(seconds) + Duration::from_millis(jitter as u64)
+}
+
+// Precedence: --api-base flag, then TW_API_BASE env var, then `api_base` in the credentials file.
+// An empty one counts as not set. Handy for pointing `tw` at a local stand-in server.
+fn api_base(flag: Option<String>, env: Option<String>, file: Option<String>) -> String {
+    let api_base = flag
+        .into_iter()
+        .chain(env)
+        .chain(file)
+        .find(|base| !base.is_empty())
+        .unwrap_or_else(|| DEFAULT_API_BASE.to_string());
+    api_base.trim_end_matches('/').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    // A stand-in for Twitter that takes the fresh bearer token and turns down everything else.
+    // Returns its base URL and a log of "<path> <authorization>" for every request
+    fn serve(fresh_token: Option<&'static str>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&log);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut lines = Vec::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    lines.push(line.trim().to_string());
                 }
-            })
-            .collect::<Vec<String>>()
-            .join(join_str)
+                let path = lines[0].split(' ').nth(1).unwrap().to_string();
+                let authorization = lines
+                    .iter()
+                    .filter_map(|line| line.split_once(": "))
+                    .find(|(name, _)| name.eq_ignore_ascii_case("authorization"))
+                    .map(|(_, value)| value.to_string())
+                    .unwrap_or_default();
+                seen.lock().unwrap().push(format!(
+                    "{} {}",
+                    path.split('?').next().unwrap(),
+                    authorization
+                ));
+                let (status, body) = match fresh_token {
+                    Some(token) if path.starts_with("/oauth2/token") => {
+                        ("200 OK", format!(r#"{{"access_token":"{}"}}"#, token))
+                    }
+                    Some(token) if authorization == format!("Bearer {}", token) => (
+                        "200 OK",
+                        String::from(r#"{"data":[],"meta":{"result_count":0}}"#),
+                    ),
+                    _ => (
+                        "401 Unauthorized",
+                        String::from(
+                            r#"{"title":"Unauthorized","type":"about:blank","status":401,"detail":"Unauthorized"}"#,
+                        ),
+                    ),
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+        (base, log)
     }
 
-    // Possible to use match on the enum if desired
-    // https://docs.rs/reqwest/0.11.6/reqwest/struct.StatusCode.html#impl-1
-    fn error(&self, res: reqwest::blocking::Response) -> TwitterError {
-        if res.status().is_server_error() {
-            TwitterError::Api(format!("Server error: {}", &res.status()))
-        } else if res.status().is_client_error() {
-            TwitterError::Api(format!("Client error: {}", &res.status()))
-        } else {
-            TwitterError::Api(format!("Unknown error: {}", &res.status()))
+    fn base_args(api_base: &str) -> BaseArgs {
+        let args = ["tw", "home", "--api-base", api_base];
+        BaseArgs::from_args(args.iter().map(|arg| arg.to_string()).collect()).unwrap()
+    }
+
+    fn app_credentials(api_key: &str) -> Credentials {
+        Credentials {
+            api_key: api_key.to_string(),
+            api_key_secret: String::from("secret"),
+            ..Default::default()
         }
     }
+
+    #[test]
+    fn api_bases() {
+        let some = |base: &str| Some(base.to_string());
+        let (flag, env, file) = (
+            some("http://flag"),
+            some("http://env/"),
+            some("http://file"),
+        );
+        assert_eq!(
+            api_base(flag.clone(), env.clone(), file.clone()),
+            "http://flag"
+        );
+        assert_eq!(api_base(flag.clone(), None, file.clone()), "http://flag");
+        assert_eq!(api_base(flag.clone(), env.clone(), None), "http://flag");
+        assert_eq!(api_base(None, env.clone(), file.clone()), "http://env");
+        assert_eq!(api_base(None, env.clone(), None), "http://env");
+        assert_eq!(api_base(None, None, file.clone()), "http://file");
+        assert_eq!(api_base(None, None, None), DEFAULT_API_BASE);
+        // Empty is the same as not set
+        assert_eq!(api_base(some(""), some(""), file), "http://file");
+        assert_eq!(api_base(None, some(""), None), DEFAULT_API_BASE);
+        assert_eq!(
+            api_base(some("http://127.0.0.1:8765//"), env, None),
+            "http://127.0.0.1:8765"
+        );
+    }
+
+    #[test]
+    fn retries() {
+        let (get, post, delete) = (Method::GET, Method::POST, Method::DELETE);
+        let unavailable = StatusCode::SERVICE_UNAVAILABLE;
+        // 429s, whatever the method
+        assert!(should_retry(&get, StatusCode::TOO_MANY_REQUESTS, 0, 3));
+        assert!(should_retry(&post, StatusCode::TOO_MANY_REQUESTS, 2, 3));
+        // 5xx only when repeating the request can't do anything twice
+        assert!(should_retry(&get, unavailable, 0, 3));
+        assert!(should_retry(&delete, StatusCode::BAD_GATEWAY, 0, 3));
+        assert!(should_retry(&get, StatusCode::INTERNAL_SERVER_ERROR, 0, 3));
+        assert!(should_retry(&get, StatusCode::GATEWAY_TIMEOUT, 0, 3));
+        assert!(!should_retry(&post, unavailable, 0, 3));
+        assert!(!should_retry(&Method::PUT, unavailable, 0, 3));
+        assert!(!should_retry(&get, StatusCode::NOT_IMPLEMENTED, 0, 3));
+        // Everything else is final
+        assert!(!should_retry(&get, StatusCode::OK, 0, 3));
+        assert!(!should_retry(&get, StatusCode::UNAUTHORIZED, 0, 3));
+        assert!(!should_retry(&get, StatusCode::NOT_FOUND, 0, 3));
+        // Up to --retry times, and not at all without it
+        assert!(!should_retry(&get, StatusCode::TOO_MANY_REQUESTS, 3, 3));
+        assert!(!should_retry(&get, unavailable, 3, 3));
+        assert!(!should_retry(&get, StatusCode::TOO_MANY_REQUESTS, 0, 0));
+    }
+
+    #[test]
+    fn backoffs() {
+        for (attempt, seconds) in [
+            (0, 1),
+            (1, 2),
+            (2, 4),
+            (5, 32),
+            (6, 60),
+            (40, 60),
+            (u32::MAX, 60),
+        ] {
+            for _ in 0..20 {
+                let wait = backoff(attempt);
+                assert!(wait >= Duration::from_secs(seconds));
+                assert!(wait < Duration::from_secs(seconds + 1));
+            }
+        }
+    }
+
+    // Both cases in one test, since they share the cache file
+    #[test]
+    fn refetches_a_rejected_bearer_token_once() {
+        let cache = env::temp_dir().join(format!("tw-test-cache-{}.toml", std::process::id()));
+        env::set_var("TW_CACHE", &cache);
+        let stale = OauthResponse {
+            access_token: String::from("STALE"),
+        };
+
+        // Twitter turned down the cached token, but hands out a new one that works
+        let (base, log) = serve(Some("FRESH"));
+        let (args, credentials) = (base_args(&base), app_credentials("rotated"));
+        let client = Client::new(&credentials, &args);
+        let (key, fingerprint) = client.bearer_cache_entry();
+        cache::set("bearer", &key, &fingerprint, &stale).unwrap();
+        assert!(client.home_v2(&String::from("1"), 5, None, None).is_ok());
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                "/2/users/1/tweets Bearer STALE",
+                "/oauth2/token Basic cm90YXRlZDpzZWNyZXQ=",
+                "/2/users/1/tweets Bearer FRESH",
+            ]
+        );
+        let cached = cache::get::<OauthResponse>("bearer", &key, &fingerprint).unwrap();
+        assert_eq!(cached.access_token, "FRESH");
+
+        // When the new one is turned down too, that's the error, not another round
+        let (base, log) = serve(None);
+        let (args, credentials) = (base_args(&base), app_credentials("revoked"));
+        let client = Client::new(&credentials, &args);
+        let (key, fingerprint) = client.bearer_cache_entry();
+        cache::set("bearer", &key, &fingerprint, &stale).unwrap();
+        match client.home_v2(&String::from("1"), 5, None, None) {
+            Err(TwitterError::Response(error)) => assert_eq!(error.status, 401),
+            other => panic!("Expected a 401, got {:?}", other.map(|_| ())),
+        }
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                "/2/users/1/tweets Bearer STALE",
+                "/oauth2/token Basic cmV2b2tlZDpzZWNyZXQ=",
+            ]
+        );
+        assert!(cache::get::<OauthResponse>("bearer", &key, &fingerprint).is_none());
+
+        let _ = fs::remove_file(&cache);
+    }
 }
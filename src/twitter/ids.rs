@@ -0,0 +1,159 @@
+// Tweets and users can be given as plain ids, or as whatever got copied out of the browser:
+// https://twitter.com/jack/status/20, x.com/jack/status/20?s=20, @jack, https://mobile.twitter.com/jack
+
+use super::super::error::TwitterError;
+use super::{Client, TwitterUser};
+use reqwest::Url;
+
+const HOSTS: [&str; 6] = [
+    "twitter.com",
+    "www.twitter.com",
+    "mobile.twitter.com",
+    "x.com",
+    "www.x.com",
+    "mobile.x.com",
+];
+
+// Paths on twitter.com that look like a profile but aren't one
+const NOT_HANDLES: [&str; 10] = [
+    "i",
+    "home",
+    "search",
+    "explore",
+    "notifications",
+    "messages",
+    "settings",
+    "intent",
+    "hashtag",
+    "share",
+];
+
+const MAX_HANDLE_LENGTH: usize = 15;
+
+#[derive(Debug, PartialEq)]
+pub enum User {
+    Id(String),
+    Handle(String),
+}
+
+fn is_id(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_digit())
+}
+
+fn is_handle(value: &str) -> bool {
+    (1..=MAX_HANDLE_LENGTH).contains(&value.len())
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// The path of a twitter.com or x.com link, None for anything else
+fn path(value: &str) -> Option<Vec<String>> {
+    let url = match value.contains("://") {
+        true => Url::parse(value),
+        false => Url::parse(&format!("https://{}", value)),
+    }
+    .ok()?;
+    let host = url.host_str()?.to_lowercase();
+    if !HOSTS.contains(&host.as_str()) {
+        return None;
+    }
+    Some(
+        url.path_segments()?
+            .filter(|segment| !segment.is_empty())
+            .map(String::from)
+            .collect(),
+    )
+}
+
+// A tweet id, or a link to a tweet (.../status/<id>, with anything after it)
+pub fn tweet_id(value: &str) -> Result<String, TwitterError> {
+    let value = value.trim();
+    if is_id(value) {
+        return Ok(value.to_string());
+    }
+    path(value)
+        .and_then(|segments| {
+            segments
+                .windows(2)
+                .find(|pair| pair[0] == "status" || pair[0] == "statuses")
+                .map(|pair| pair[1].to_string())
+        })
+        .filter(|id| is_id(id))
+        .ok_or_else(|| {
+            TwitterError::Invalid(format!(
+                "{} isn't a tweet id or a link to a tweet, like 20 or https://twitter.com/jack/status/20",
+                value
+            ))
+        })
+}
+
+// A user id, @handle, or a link to a profile. A handle that's all digits needs the @, or it's taken for an id
+pub fn user(value: &str) -> Result<User, TwitterError> {
+    let value = value.trim();
+    if is_id(value) {
+        return Ok(User::Id(value.to_string()));
+    }
+    let handle = match value.strip_prefix('@') {
+        Some(handle) => Some(handle.to_string()),
+        None if is_handle(value) => Some(value.to_string()),
+        None => path(value)
+            .and_then(|segments| segments.into_iter().next())
+            .filter(|handle| !NOT_HANDLES.contains(&handle.to_lowercase().as_str())),
+    };
+    handle
+        .filter(|handle| is_handle(handle))
+        .map(User::Handle)
+        .ok_or_else(|| {
+            TwitterError::Invalid(format!(
+                "{} isn't a user id, @handle or link to a profile, like @jack or https://twitter.com/jack",
+                value
+            ))
+        })
+}
+
+// Looks the user up, since a handle has to be turned into an id before most endpoints will take it
+pub fn lookup_user(client: &Client, value: &str) -> Result<TwitterUser, TwitterError> {
+    match user(value)? {
+        User::Id(id) => client.user_v2(&id),
+        User::Handle(handle) => client.user_by_username_v2(&handle),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tweet_ids() {
+        assert_eq!(tweet_id("20").unwrap(), "20");
+        assert_eq!(tweet_id(" 20 ").unwrap(), "20");
+        assert_eq!(
+            tweet_id("https://twitter.com/jack/status/20").unwrap(),
+            "20"
+        );
+        assert_eq!(tweet_id("x.com/jack/status/20?s=20&t=abc").unwrap(), "20");
+        assert_eq!(
+            tweet_id("https://mobile.twitter.com/jack/status/20/photo/1").unwrap(),
+            "20"
+        );
+        assert_eq!(tweet_id("https://x.com/i/web/status/20#m").unwrap(), "20");
+        assert!(tweet_id("").is_err());
+        assert!(tweet_id("12ab").is_err());
+        assert!(tweet_id("https://twitter.com/jack").is_err());
+        assert!(tweet_id("https://example.com/jack/status/20").is_err());
+    }
+
+    #[test]
+    fn users() {
+        let handle = |handle: &str| User::Handle(handle.to_string());
+        assert_eq!(user("12").unwrap(), User::Id("12".to_string()));
+        assert_eq!(user("@jack").unwrap(), handle("jack"));
+        assert_eq!(user("@12").unwrap(), handle("12"));
+        assert_eq!(user("jack").unwrap(), handle("jack"));
+        assert_eq!(user("https://x.com/jack?lang=en").unwrap(), handle("jack"));
+        assert_eq!(user("twitter.com/jack/status/20").unwrap(), handle("jack"));
+        assert!(user("@").is_err());
+        assert!(user("@way_too_long_for_a_handle").is_err());
+        assert!(user("https://twitter.com/home").is_err());
+        assert!(user("not a handle").is_err());
+    }
+}
@@ -0,0 +1,193 @@
+// Pictures, GIFs and videos to attach to tweets, and what Twitter will take
+// https://developer.twitter.com/en/docs/twitter-api/v1/media/upload-media/uploading-media/media-best-practices
+
+use super::super::error::TwitterError;
+use std::fs;
+use std::path::Path;
+
+pub const MAX_IMAGES: usize = 4;
+pub const MAX_ALT_TEXT: usize = 1000;
+const MB: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Image,
+    Gif,
+    Video,
+}
+
+impl Kind {
+    // Goes with INIT so Twitter knows how to process it. GIFs need their own to stay animated
+    pub fn category(&self) -> &'static str {
+        match self {
+            Kind::Image => "tweet_image",
+            Kind::Gif => "tweet_gif",
+            Kind::Video => "tweet_video",
+        }
+    }
+
+    fn max_bytes(&self) -> u64 {
+        match self {
+            Kind::Image => 5 * MB,
+            Kind::Gif => 15 * MB,
+            Kind::Video => 512 * MB,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Media {
+    pub path: String,
+    pub kind: Kind,
+    pub media_type: &'static str,
+    pub alt_text: Option<String>,
+    // Which tweet of a thread it goes on, counting from 0
+    pub tweet: usize,
+}
+
+impl Media {
+    // Everything that can be checked without uploading is checked here, so a thread doesn't stop at the
+    // third tweet because its picture was misspelled
+    pub fn open(path: &str, tweet: usize, alt_text: Option<String>) -> Result<Self, TwitterError> {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        let (kind, media_type) = match extension.as_str() {
+            "jpg" | "jpeg" => (Kind::Image, "image/jpeg"),
+            "png" => (Kind::Image, "image/png"),
+            "webp" => (Kind::Image, "image/webp"),
+            "gif" => (Kind::Gif, "image/gif"),
+            "mp4" => (Kind::Video, "video/mp4"),
+            "mov" => (Kind::Video, "video/quicktime"),
+            _ => {
+                return Err(TwitterError::Invalid(format!(
+                    "Can't attach {}, Twitter takes jpg, png, webp, gif, mp4 and mov files",
+                    path
+                )))
+            }
+        };
+        let size = fs::metadata(path)
+            .map_err(|err| TwitterError::Invalid(format!("Can't read {}: {}", path, err)))?
+            .len();
+        if size > kind.max_bytes() {
+            return Err(TwitterError::Invalid(format!(
+                "{} is {:.1}MB, Twitter only takes up to {}MB for {}",
+                path,
+                size as f64 / MB as f64,
+                kind.max_bytes() / MB,
+                extension
+            )));
+        }
+        if let Some(ref alt_text) = alt_text {
+            if alt_text.chars().count() > MAX_ALT_TEXT {
+                return Err(TwitterError::Invalid(format!(
+                    "Alt text for {} is over the limit of {} characters",
+                    path, MAX_ALT_TEXT
+                )));
+            }
+        }
+        Ok(Self {
+            path: path.to_string(),
+            kind,
+            media_type,
+            alt_text,
+            tweet,
+        })
+    }
+
+    // Images go up in one request. GIFs and videos have to be chunked, and processed before they can be used
+    pub fn is_chunked(&self) -> bool {
+        self.kind != Kind::Image
+    }
+}
+
+// `2:photo.png` attaches photo.png to the second tweet of a thread, plain `photo.png` to the first.
+// Returns the tweet counting from 0
+pub fn parse_spec(spec: &str) -> (usize, &str) {
+    match spec.split_once(':') {
+        Some((tweet, path)) if !tweet.is_empty() && tweet.chars().all(|c| c.is_ascii_digit()) => {
+            match tweet.parse::<usize>() {
+                Ok(tweet) if tweet > 0 => (tweet - 1, path),
+                _ => (0, spec),
+            }
+        }
+        _ => (0, spec),
+    }
+}
+
+// A tweet gets up to four images, or a single GIF or video
+pub fn validate(media: &[Media], tweets: usize) -> Result<(), TwitterError> {
+    for attachment in media {
+        if attachment.tweet >= tweets {
+            return Err(TwitterError::Invalid(format!(
+                "{} is for tweet {}, but there {} only {}",
+                attachment.path,
+                attachment.tweet + 1,
+                if tweets == 1 { "is" } else { "are" },
+                tweets
+            )));
+        }
+    }
+    for tweet in 0..tweets {
+        let attached = media
+            .iter()
+            .filter(|attachment| attachment.tweet == tweet)
+            .collect::<Vec<_>>();
+        let images = attached
+            .iter()
+            .filter(|attachment| attachment.kind == Kind::Image)
+            .count();
+        let which = match tweets {
+            1 => String::from("A tweet"),
+            _ => format!("Tweet {}", tweet + 1),
+        };
+        if images < attached.len() && attached.len() > 1 {
+            return Err(TwitterError::Invalid(format!(
+                "{} can have a single GIF or video, or up to {} images, not both",
+                which, MAX_IMAGES
+            )));
+        }
+        if images > MAX_IMAGES {
+            return Err(TwitterError::Invalid(format!(
+                "{} can have up to {} images, not {}",
+                which, MAX_IMAGES, images
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attachment(kind: Kind, tweet: usize) -> Media {
+        Media {
+            path: String::from("file"),
+            kind,
+            media_type: "image/png",
+            alt_text: None,
+            tweet,
+        }
+    }
+
+    #[test]
+    fn specs() {
+        assert_eq!(parse_spec("photo.png"), (0, "photo.png"));
+        assert_eq!(parse_spec("2:photo.png"), (1, "photo.png"));
+        assert_eq!(parse_spec("C:\\photo.png"), (0, "C:\\photo.png"));
+        assert_eq!(parse_spec("0:photo.png"), (0, "0:photo.png"));
+    }
+
+    #[test]
+    fn limits_per_tweet() {
+        let four = vec![attachment(Kind::Image, 0); 4];
+        assert!(validate(&four, 1).is_ok());
+        assert!(validate(&vec![attachment(Kind::Image, 0); 5], 1).is_err());
+        assert!(validate(&[attachment(Kind::Video, 0), attachment(Kind::Image, 0)], 1).is_err());
+        assert!(validate(&[attachment(Kind::Gif, 0), attachment(Kind::Gif, 1)], 2).is_ok());
+        assert!(validate(&[attachment(Kind::Image, 2)], 2).is_err());
+    }
+}
@@ -3,3 +3,22 @@ pub use client::Client;
 
 pub mod response;
 pub use response::*;
+
+pub mod rate_limit;
+pub use rate_limit::RateLimit;
+
+pub mod request;
+pub use request::{Auth, NewTweet, Poll, ReplySettings, Request};
+
+pub mod oauth;
+pub mod oauth2;
+
+pub mod paginate;
+pub use paginate::HomePages;
+
+pub mod text;
+
+pub mod ids;
+
+pub mod media;
+pub use media::Media;
@@ -0,0 +1,381 @@
+// Twitter takes their authorization seriously
+// https://developer.twitter.com/en/docs/authentication/oauth-1-0a/creating-a-signature
+// https://datatracker.ietf.org/doc/html/rfc5849#section-3.4
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Signs requests with OAuth 1.0a HMAC-SHA1.
+// `token` is optional because the first leg of the PIN flow (oauth/request_token) doesn't have one yet.
+pub struct Signer<'a> {
+    pub consumer_key: &'a str,
+    pub consumer_secret: &'a str,
+    pub token: Option<&'a str>,
+    pub token_secret: &'a str,
+    // Extra `oauth_*` protocol parameters, e.g. oauth_callback or oauth_verifier
+    pub extra: Vec<(String, String)>,
+}
+
+impl<'a> Signer<'a> {
+    pub fn new(
+        consumer_key: &'a str,
+        consumer_secret: &'a str,
+        token: Option<&'a str>,
+        token_secret: &'a str,
+    ) -> Self {
+        Self {
+            consumer_key,
+            consumer_secret,
+            token,
+            token_secret,
+            extra: Vec::new(),
+        }
+    }
+
+    pub fn with_oauth_param(mut self, key: &str, value: &str) -> Self {
+        self.extra.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    // Builds the Authorization header for a request.
+    // `url` may include a query string, which will be signed.
+    // `body_params` are form-encoded body parameters; JSON bodies are not signed.
+    pub fn authorization(
+        &self,
+        method: &str,
+        url: &str,
+        body_params: &[(String, String)],
+    ) -> String {
+        self.authorization_with(method, url, body_params, &nonce(), &timestamp())
+    }
+
+    fn authorization_with(
+        &self,
+        method: &str,
+        url: &str,
+        body_params: &[(String, String)],
+        nonce: &str,
+        timestamp: &str,
+    ) -> String {
+        let oauth_params = self.oauth_params(nonce, timestamp);
+        let signature = self.signature(method, url, &oauth_params, body_params);
+
+        let mut header_params = oauth_params;
+        header_params.push(("oauth_signature".to_string(), signature));
+        header_params.sort();
+        let header = header_params
+            .iter()
+            .map(|(key, value)| format!("{}=\"{}\"", percent_encode(key), percent_encode(value)))
+            .collect::<Vec<String>>()
+            .join(", ");
+        format!("OAuth {}", header)
+    }
+
+    fn oauth_params(&self, nonce: &str, timestamp: &str) -> Vec<(String, String)> {
+        let mut params = vec![
+            (
+                "oauth_consumer_key".to_string(),
+                self.consumer_key.to_string(),
+            ),
+            ("oauth_nonce".to_string(), nonce.to_string()),
+            (
+                "oauth_signature_method".to_string(),
+                "HMAC-SHA1".to_string(),
+            ),
+            ("oauth_timestamp".to_string(), timestamp.to_string()),
+            ("oauth_version".to_string(), "1.0".to_string()),
+        ];
+        if let Some(token) = self.token {
+            params.push(("oauth_token".to_string(), token.to_string()));
+        }
+        params.extend(self.extra.iter().cloned());
+        params
+    }
+
+    fn signature(
+        &self,
+        method: &str,
+        url: &str,
+        oauth_params: &[(String, String)],
+        body_params: &[(String, String)],
+    ) -> String {
+        let params = [oauth_params, body_params].concat();
+        let base_string = signature_base_string(method, url, &params);
+        let signing_key = format!(
+            "{}&{}",
+            percent_encode(self.consumer_secret),
+            percent_encode(self.token_secret)
+        );
+        // This lib looks a little jank but it works and its tiny https://docs.rs/hmac-sha1/0.1.3/hmacsha1/
+        let hashed = hmacsha1::hmac_sha1(signing_key.as_bytes(), base_string.as_bytes());
+        base64::encode(hashed)
+    }
+}
+
+// https://datatracker.ietf.org/doc/html/rfc5849#section-3.4.1
+// METHOD&encoded(base uri)&encoded(normalized parameters)
+pub fn signature_base_string(method: &str, url: &str, params: &[(String, String)]) -> String {
+    let (base_uri, query) = match url.split_once('?') {
+        Some((base_uri, query)) => (base_uri, query),
+        None => (url, ""),
+    };
+    let mut all_params = parse_query(query);
+    all_params.extend(params.iter().cloned());
+
+    format!(
+        "{}&{}&{}",
+        method.to_uppercase(),
+        percent_encode(&normalize_base_uri(base_uri)),
+        percent_encode(&normalize_parameters(&all_params))
+    )
+}
+
+// https://datatracker.ietf.org/doc/html/rfc5849#section-3.4.1.3.2
+// Encode, then sort by name and value, then join
+pub fn normalize_parameters(params: &[(String, String)]) -> String {
+    let mut encoded = params
+        .iter()
+        .map(|(key, value)| (percent_encode(key), percent_encode(value)))
+        .collect::<Vec<(String, String)>>();
+    encoded.sort();
+    encoded
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+// https://datatracker.ietf.org/doc/html/rfc5849#section-3.4.1.2
+// Lowercase scheme and host, drop default ports, drop query and fragment
+fn normalize_base_uri(url: &str) -> String {
+    let url = url.split('#').next().unwrap_or("");
+    let (scheme, rest) = match url.split_once("://") {
+        Some((scheme, rest)) => (scheme.to_lowercase(), rest),
+        None => return url.to_string(),
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let mut authority = authority.to_lowercase();
+    let default_port = if scheme == "https" { ":443" } else { ":80" };
+    if authority.ends_with(default_port) {
+        authority.truncate(authority.len() - default_port.len());
+    }
+    format!("{}://{}{}", scheme, authority, path)
+}
+
+// Query strings may be form encoded ("+" for spaces) or percent encoded.
+// Also handy for the form encoded bodies that the oauth/* endpoints send back.
+pub fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => (percent_decode(key), percent_decode(value)),
+            None => (percent_decode(pair), String::new()),
+        })
+        .collect()
+}
+
+// https://datatracker.ietf.org/doc/html/rfc5849#section-3.6
+// Everything except ALPHA, DIGIT, "-", ".", "_", "~" gets encoded as UTF-8 bytes with uppercase hex
+pub fn percent_encode(input: &str) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+pub fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            // from_str_radix takes a leading +, so %+1 would come out as 0x01 without the hex digit check
+            b'%' if i + 2 < bytes.len()
+                && bytes[i + 1].is_ascii_hexdigit()
+                && bytes[i + 2].is_ascii_hexdigit() =>
+            {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+                match u8::from_str_radix(hex, 16) {
+                    Ok(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    Err(_) => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+// 32 random bytes, hex encoded. Twitter just wants something unique per request
+pub fn nonce() -> String {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).expect("Could not generate a random nonce");
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn timestamp() -> String {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs().to_string(),
+        // truly no idea what would cause this
+        Err(err) => panic!("{}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The worked example from Twitter's docs
+    // https://developer.twitter.com/en/docs/authentication/oauth-1-0a/creating-a-signature
+    fn twitter_example_signer() -> Signer<'static> {
+        Signer::new(
+            "xvz1evFS4wEEPTGEFPHBog",
+            "kAcSOqF21Fu85e7zjz7ZN2U4ZRhfV3WpwPAoE3Z7kBw",
+            Some("370773112-GmHxMAgYyLbNEtIKZeRNFsMKPR9EyMZeS9weJAEb"),
+            "LswwdoUaIvS8ltyTt5jkRh4J50vUPVVHtR2YPi5kE",
+        )
+    }
+
+    const NONCE: &str = "kYjzVBB8Y0ZFabxSWbWovY3uYSQ2pTgmZeNu2VS4cg";
+    const TIMESTAMP: &str = "1318622958";
+
+    fn status_params() -> Vec<(String, String)> {
+        vec![(
+            "status".to_string(),
+            "Hello Ladies + Gentlemen, a signed OAuth request!".to_string(),
+        )]
+    }
+
+    #[test]
+    fn percent_encodes_per_rfc_5849() {
+        assert_eq!(
+            percent_encode("Ladies + Gentlemen"),
+            "Ladies%20%2B%20Gentlemen"
+        );
+        assert_eq!(
+            percent_encode("An encoded string!"),
+            "An%20encoded%20string%21"
+        );
+        assert_eq!(
+            percent_encode("Dogs, Cats & Mice"),
+            "Dogs%2C%20Cats%20%26%20Mice"
+        );
+        assert_eq!(percent_encode("☃"), "%E2%98%83");
+        assert_eq!(percent_encode("-._~*'()"), "-._~%2A%27%28%29");
+    }
+
+    #[test]
+    fn percent_decodes() {
+        assert_eq!(
+            percent_decode("Ladies%20%2B%20Gentlemen+too"),
+            "Ladies + Gentlemen too"
+        );
+        assert_eq!(percent_decode("%E2%98%83%e2%98%83"), "☃☃");
+        // Anything that isn't two hex digits is kept as it is
+        assert_eq!(percent_decode("%+1"), "% 1");
+        assert_eq!(percent_decode("%-1%1g%zz"), "%-1%1g%zz");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%4"), "%4");
+        assert_eq!(percent_decode("%%41"), "%A");
+    }
+
+    #[test]
+    fn builds_twitter_example_parameter_string() {
+        let signer = twitter_example_signer();
+        let mut params = signer.oauth_params(NONCE, TIMESTAMP);
+        params.extend(status_params());
+        params.push(("include_entities".to_string(), "true".to_string()));
+        assert_eq!(
+            normalize_parameters(&params),
+            "include_entities=true&oauth_consumer_key=xvz1evFS4wEEPTGEFPHBog&oauth_nonce=kYjzVBB8Y0ZFabxSWbWovY3uYSQ2pTgmZeNu2VS4cg&oauth_signature_method=HMAC-SHA1&oauth_timestamp=1318622958&oauth_token=370773112-GmHxMAgYyLbNEtIKZeRNFsMKPR9EyMZeS9weJAEb&oauth_version=1.0&status=Hello%20Ladies%20%2B%20Gentlemen%2C%20a%20signed%20OAuth%20request%21"
+        );
+    }
+
+    #[test]
+    fn builds_twitter_example_base_string_from_query() {
+        let signer = twitter_example_signer();
+        let mut params = signer.oauth_params(NONCE, TIMESTAMP);
+        params.extend(status_params());
+        assert_eq!(
+            signature_base_string(
+                "post",
+                "HTTPS://API.Twitter.com:443/1.1/statuses/update.json?include_entities=true",
+                &params
+            ),
+            "POST&https%3A%2F%2Fapi.twitter.com%2F1.1%2Fstatuses%2Fupdate.json&include_entities%3Dtrue%26oauth_consumer_key%3Dxvz1evFS4wEEPTGEFPHBog%26oauth_nonce%3DkYjzVBB8Y0ZFabxSWbWovY3uYSQ2pTgmZeNu2VS4cg%26oauth_signature_method%3DHMAC-SHA1%26oauth_timestamp%3D1318622958%26oauth_token%3D370773112-GmHxMAgYyLbNEtIKZeRNFsMKPR9EyMZeS9weJAEb%26oauth_version%3D1.0%26status%3DHello%2520Ladies%2520%252B%2520Gentlemen%252C%2520a%2520signed%2520OAuth%2520request%2521"
+        );
+    }
+
+    #[test]
+    fn signs_twitter_example() {
+        let signer = twitter_example_signer();
+        let oauth_params = signer.oauth_params(NONCE, TIMESTAMP);
+        let signature = signer.signature(
+            "POST",
+            "https://api.twitter.com/1.1/statuses/update.json?include_entities=true",
+            &oauth_params,
+            &status_params(),
+        );
+        assert_eq!(signature, "hCtSmYh+iHYCEqBWrE7C7hYmtUk=");
+    }
+
+    #[test]
+    fn builds_twitter_example_header() {
+        let header = twitter_example_signer().authorization_with(
+            "POST",
+            "https://api.twitter.com/1.1/statuses/update.json?include_entities=true",
+            &status_params(),
+            NONCE,
+            TIMESTAMP,
+        );
+        assert_eq!(
+            header,
+            "OAuth oauth_consumer_key=\"xvz1evFS4wEEPTGEFPHBog\", oauth_nonce=\"kYjzVBB8Y0ZFabxSWbWovY3uYSQ2pTgmZeNu2VS4cg\", oauth_signature=\"hCtSmYh%2BiHYCEqBWrE7C7hYmtUk%3D\", oauth_signature_method=\"HMAC-SHA1\", oauth_timestamp=\"1318622958\", oauth_token=\"370773112-GmHxMAgYyLbNEtIKZeRNFsMKPR9EyMZeS9weJAEb\", oauth_version=\"1.0\""
+        );
+    }
+
+    #[test]
+    fn query_string_and_form_params_sign_identically() {
+        let signer = twitter_example_signer();
+        let oauth_params = signer.oauth_params(NONCE, TIMESTAMP);
+        let from_query = signer.signature(
+            "GET",
+            "https://api.twitter.com/2/users/1/tweets?max_results=5&tweet.fields=created_at%2Cauthor_id",
+            &oauth_params,
+            &[],
+        );
+        let from_params = signer.signature(
+            "GET",
+            "https://api.twitter.com/2/users/1/tweets",
+            &oauth_params,
+            &[
+                (
+                    "tweet.fields".to_string(),
+                    "created_at,author_id".to_string(),
+                ),
+                ("max_results".to_string(), "5".to_string()),
+            ],
+        );
+        assert_eq!(from_query, from_params);
+    }
+
+    #[test]
+    fn nonces_are_unique() {
+        assert_ne!(nonce(), nonce());
+        assert_eq!(nonce().len(), 64);
+    }
+}
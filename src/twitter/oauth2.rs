@@ -0,0 +1,181 @@
+// OAuth 2.0 Authorization Code Flow with PKCE
+// https://developer.twitter.com/en/docs/authentication/oauth-2-0/authorization-code
+// https://datatracker.ietf.org/doc/html/rfc7636
+
+use super::super::error::TwitterError;
+use super::oauth;
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::time::Duration;
+
+// Enough to read your timeline and bookmarks, and to keep a refresh token around
+pub const DEFAULT_SCOPES: &str =
+    "tweet.read tweet.write users.read like.read like.write bookmark.read offline.access";
+
+// The app's callback URL in the developer portal has to match this exactly
+pub const DEFAULT_PORT: u16 = 8739;
+
+pub struct Pkce {
+    pub verifier: String,
+    pub challenge: String,
+    pub state: String,
+}
+
+impl Pkce {
+    pub fn new() -> Self {
+        let verifier = random_string();
+        let challenge =
+            base64::encode_config(Sha256::digest(verifier.as_bytes()), base64::URL_SAFE_NO_PAD);
+        Self {
+            verifier,
+            challenge,
+            state: random_string(),
+        }
+    }
+}
+
+// 32 random bytes => 43 URL safe characters, which is the shortest verifier the spec allows
+fn random_string() -> String {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).expect("Could not generate a random PKCE verifier");
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+pub fn redirect_uri(port: u16) -> String {
+    format!("http://127.0.0.1:{}/callback", port)
+}
+
+// Bound before the authorize URL is shown, so an early redirect can't beat us to it
+pub fn listen(port: u16) -> Result<TcpListener, TwitterError> {
+    Ok(TcpListener::bind(("127.0.0.1", port))?)
+}
+
+// What a request to the loopback listener means for the login. None if it isn't the callback at all,
+// e.g. the browser asking for /favicon.ico or preconnecting
+fn callback(target: &str, state: &str) -> Option<Result<String, TwitterError>> {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let params = oauth::parse_query(query);
+    let param = |name: &str| {
+        params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.to_string())
+    };
+    if path != "/callback"
+        || ["state", "code", "error"]
+            .iter()
+            .all(|name| param(name).is_none())
+    {
+        return None;
+    }
+
+    Some(match (param("state"), param("code"), param("error")) {
+        (_, _, Some(error)) => Err(TwitterError::Auth(format!(
+            "Authorization was not granted: {}",
+            error
+        ))),
+        (Some(ref returned_state), Some(code), None) if returned_state == state => Ok(code),
+        (Some(_), Some(_), None) => Err(TwitterError::Auth(
+            "State mismatch in OAuth 2.0 callback, not trusting it".to_string(),
+        )),
+        _ => Err(TwitterError::Auth(
+            "OAuth 2.0 callback did not include an authorization code".to_string(),
+        )),
+    })
+}
+
+// Waits for the browser to be redirected back to us, then hands back the authorization code.
+// Anything else that shows up in the meantime gets a 404
+pub fn wait_for_code(listener: &TcpListener, state: &str) -> Result<String, TwitterError> {
+    loop {
+        let (mut stream, _) = listener.accept()?;
+
+        // GET /callback?state=...&code=... HTTP/1.1
+        // A preconnect can sit there without ever sending anything, so don't wait on one forever
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        let mut request_line = String::new();
+        if BufReader::new(&stream)
+            .read_line(&mut request_line)
+            .is_err()
+        {
+            continue;
+        }
+        let target = request_line.split_whitespace().nth(1).unwrap_or("");
+
+        let (status, message, result) = match callback(target, state) {
+            None => ("404 Not Found", "Not found", None),
+            Some(Ok(code)) => (
+                "200 OK",
+                "🐤 All set! You can close this tab and head back to your terminal.",
+                Some(Ok(code)),
+            ),
+            Some(Err(err)) => (
+                "200 OK",
+                "😬 Something went wrong. Head back to your terminal for details.",
+                Some(Err(err)),
+            ),
+        };
+        // The browser may well have given up on a favicon by now, that's not worth failing the login over
+        let written = write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            message.len(),
+            message
+        );
+        if let Some(result) = result {
+            written?;
+            return result;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpStream;
+    use std::thread;
+
+    #[test]
+    fn callbacks() {
+        assert!(callback("/favicon.ico", "s").is_none());
+        assert!(callback("/", "s").is_none());
+        assert!(callback("/callback", "s").is_none());
+        assert!(callback("/other?state=s&code=c", "s").is_none());
+        assert_eq!(
+            callback("/callback?state=s&code=c", "s").unwrap().unwrap(),
+            "c"
+        );
+        assert!(callback("/callback?state=x&code=c", "s").unwrap().is_err());
+        assert!(callback("/callback?state=s", "s").unwrap().is_err());
+        assert!(callback("/callback?error=access_denied&state=s", "s")
+            .unwrap()
+            .is_err());
+    }
+
+    #[test]
+    fn skips_requests_that_arent_the_callback() {
+        let listener = listen(0).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let get = move |target: &'static str| {
+            let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            write!(stream, "GET {} HTTP/1.1\r\nHost: 127.0.0.1\r\n\r\n", target).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+        let browser = thread::spawn(move || {
+            // A preconnect that never sends anything
+            drop(TcpStream::connect(("127.0.0.1", port)).unwrap());
+            let favicon = get("/favicon.ico");
+            let callback = get("/callback?state=s&code=c");
+            (favicon, callback)
+        });
+        assert_eq!(wait_for_code(&listener, "s").unwrap(), "c");
+        let (favicon, callback) = browser.join().unwrap();
+        assert!(favicon.starts_with("HTTP/1.1 404"));
+        assert!(callback.starts_with("HTTP/1.1 200"));
+    }
+}
@@ -0,0 +1,135 @@
+use super::super::error::TwitterError;
+use super::{Client, TwitterHomeItem};
+
+// Which page comes next. Kept apart from the requests so it can be tested without a server
+#[derive(Debug, Default)]
+struct Cursor {
+    // The token for the page after the last one that came back. After an error, the one that failed
+    next: Option<String>,
+    // The token the last page that came back was fetched with
+    current: Option<String>,
+    done: bool,
+}
+
+impl Cursor {
+    fn succeeded(&mut self, next: Option<String>) {
+        self.current = self.next.take();
+        self.next = next;
+        self.done = self.next.is_none();
+    }
+
+    // Keeps `next`, so the failed page can be asked for again
+    fn failed(&mut self) {
+        self.done = true;
+    }
+}
+
+// Follows meta.next_token through your own tweets, one request per page.
+// Stops after the last page or the first error, whichever comes first.
+pub struct HomePages<'a, 'c> {
+    client: &'a Client<'c>,
+    user_id: String,
+    page_size: i32,
+    start_time: Option<String>,
+    cursor: Cursor,
+}
+
+impl<'a, 'c> HomePages<'a, 'c> {
+    pub fn new(
+        client: &'a Client<'c>,
+        user_id: &str,
+        page_size: i32,
+        start_time: Option<String>,
+        pagination_token: Option<String>,
+    ) -> Self {
+        Self {
+            client,
+            user_id: user_id.to_string(),
+            page_size,
+            start_time,
+            cursor: Cursor {
+                next: pagination_token,
+                ..Default::default()
+            },
+        }
+    }
+
+    // Where to pick up again with --next-token: the page after the last one, or the one that failed.
+    // None once every page has been fetched
+    pub fn next_token(&self) -> Option<&String> {
+        self.cursor.next.as_ref()
+    }
+
+    // Where to fetch the last page again, for when only part of it got used. None if it was the first page
+    pub fn page_token(&self) -> Option<&String> {
+        self.cursor.current.as_ref()
+    }
+}
+
+impl<'a, 'c> Iterator for HomePages<'a, 'c> {
+    type Item = Result<Vec<TwitterHomeItem>, TwitterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.cursor.done {
+            return None;
+        }
+        self.client.wait_for_rate_limit("page");
+
+        let page = self.client.home_v2(
+            &self.user_id,
+            self.page_size,
+            self.cursor.next.clone(),
+            self.start_time.clone(),
+        );
+        match page {
+            Ok(page) => {
+                self.cursor
+                    .succeeded(page.meta.and_then(|meta| meta.next_token));
+                Some(Ok(page.data))
+            }
+            Err(err) => {
+                self.cursor.failed();
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(token: &str) -> Option<String> {
+        Some(token.to_string())
+    }
+
+    #[test]
+    fn follows_pages_to_the_end() {
+        let mut cursor = Cursor::default();
+        cursor.succeeded(token("b"));
+        assert_eq!(
+            (cursor.current.clone(), cursor.next.clone()),
+            (None, token("b"))
+        );
+        assert!(!cursor.done);
+        cursor.succeeded(None);
+        assert_eq!(
+            (cursor.current.clone(), cursor.next.clone()),
+            (token("b"), None)
+        );
+        assert!(cursor.done);
+    }
+
+    #[test]
+    fn keeps_the_token_of_a_failed_page() {
+        let mut cursor = Cursor {
+            next: token("a"),
+            ..Default::default()
+        };
+        cursor.succeeded(token("b"));
+        cursor.failed();
+        assert!(cursor.done);
+        assert_eq!(cursor.next, token("b"));
+        assert_eq!(cursor.current, token("a"));
+    }
+}
@@ -0,0 +1,49 @@
+use reqwest::header::HeaderMap;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Parsed from the x-rate-limit-* headers that Twitter sends back on (almost) every response
+// https://developer.twitter.com/en/docs/twitter-api/rate-limits
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    pub limit: u32,
+    pub remaining: u32,
+    pub reset: u64, // epoch seconds
+}
+
+impl RateLimit {
+    pub fn from_headers(headers: &HeaderMap) -> Option<RateLimit> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok())
+        };
+        Some(RateLimit {
+            limit: header("x-rate-limit-limit")? as u32,
+            remaining: header("x-rate-limit-remaining")? as u32,
+            reset: header("x-rate-limit-reset")?,
+        })
+    }
+
+    // How long until the window resets, plus a second of slack for clock drift
+    pub fn wait(&self) -> Duration {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        Duration::from_secs(self.reset.saturating_sub(now) + 1)
+    }
+}
+
+impl fmt::Display for RateLimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} requests remaining, resets in {}s",
+            self.remaining,
+            self.limit,
+            self.wait().as_secs()
+        )
+    }
+}
@@ -0,0 +1,280 @@
+use super::super::error::TwitterError;
+use reqwest::Method;
+use serde_json::{json, Value};
+
+// How a request proves who it is
+pub enum Auth {
+    // OAuth 1.0a user context, signed with all four keys from the credentials file
+    User,
+    // OAuth 2.0 app-only Bearer token, fetched with the api key and secret
+    App,
+    // HTTP Basic auth with the api key and secret. Only used to fetch the Bearer token
+    Basic,
+    // OAuth 2.0 user context Bearer token, for endpoints that need scopes (e.g. bookmarks).
+    // Refreshed automatically when it expires
+    OAuth2User,
+    // The OAuth 2.0 client itself, for the token endpoint.
+    // Basic auth with the client id and secret for confidential clients, nothing for public ones
+    OAuth2Client,
+    // Part of the PIN-based login flow: signed with the api key and secret plus whatever
+    // temporary token we have so far, and extra oauth_* params like oauth_callback or oauth_verifier
+    Login {
+        token: Option<String>,
+        token_secret: String,
+        oauth_params: Vec<(String, String)>,
+    },
+}
+
+// Everything the client needs to know to send a request, sign it, and dump the result.
+//
+// Example
+//    Request::get("/2/users/me")
+//        .query("user.fields", "created_at")
+//        .name("me")
+pub struct Request {
+    pub method: Method,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub body: Option<Value>,
+    // Form encoded body, only some of the oauth endpoints want this
+    pub form: Vec<(String, String)>,
+    pub auth: Auth,
+    // Used for --dump file names, e.g. "home" => ~/home-1638659354123.json
+    pub name: String,
+}
+
+impl Request {
+    pub fn new(method: Method, path: &str) -> Self {
+        Self {
+            method,
+            path: path.to_string(),
+            query: Vec::new(),
+            body: None,
+            form: Vec::new(),
+            auth: Auth::User,
+            name: "response".to_string(),
+        }
+    }
+
+    pub fn get(path: &str) -> Self {
+        Self::new(Method::GET, path)
+    }
+
+    pub fn post(path: &str) -> Self {
+        Self::new(Method::POST, path)
+    }
+
+    pub fn delete(path: &str) -> Self {
+        Self::new(Method::DELETE, path)
+    }
+
+    pub fn query<T: ToString>(mut self, key: &str, value: T) -> Self {
+        self.query.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn json(mut self, body: Value) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    pub fn form<T: ToString>(mut self, key: &str, value: T) -> Self {
+        self.form.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn auth(mut self, auth: Auth) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+}
+
+// The body of POST /2/tweets
+// https://developer.twitter.com/en/docs/twitter-api/tweets/manage-tweets/api-reference/post-tweets
+#[derive(Debug, Default, Clone)]
+pub struct NewTweet {
+    pub text: String,
+    pub in_reply_to_tweet_id: Option<String>,
+    pub media_ids: Vec<String>,
+    pub quote_tweet_id: Option<String>,
+    pub poll: Option<Poll>,
+    pub reply_settings: Option<ReplySettings>,
+    pub for_super_followers_only: bool,
+    pub place_id: Option<String>,
+}
+
+impl NewTweet {
+    pub fn json(&self) -> Value {
+        let mut body = json!({
+            "text": self.text,
+        });
+        if let Some(ref id) = self.in_reply_to_tweet_id {
+            body["reply"] = json!({
+                "in_reply_to_tweet_id": id,
+            });
+        }
+        if !self.media_ids.is_empty() {
+            body["media"] = json!({
+                "media_ids": self.media_ids,
+            });
+        }
+        if let Some(ref id) = self.quote_tweet_id {
+            body["quote_tweet_id"] = json!(id);
+        }
+        if let Some(ref poll) = self.poll {
+            body["poll"] = json!({
+                "options": poll.options,
+                "duration_minutes": poll.duration_minutes,
+            });
+        }
+        if let Some(ref reply_settings) = self.reply_settings {
+            body["reply_settings"] = json!(reply_settings.as_str());
+        }
+        if self.for_super_followers_only {
+            body["for_super_followers_only"] = json!(true);
+        }
+        if let Some(ref id) = self.place_id {
+            body["geo"] = json!({
+                "place_id": id,
+            });
+        }
+        body
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Poll {
+    pub options: Vec<String>,
+    pub duration_minutes: u32,
+}
+
+impl Poll {
+    pub const MIN_OPTIONS: usize = 2;
+    pub const MAX_OPTIONS: usize = 4;
+    pub const MAX_OPTION_LENGTH: usize = 25;
+    // 5 minutes to 7 days
+    pub const MIN_MINUTES: u32 = 5;
+    pub const MAX_MINUTES: u32 = 7 * 24 * 60;
+
+    // `options` is comma separated, e.g. "Cats,Dogs,Neither"
+    pub fn new(options: &str, duration_minutes: u32) -> Result<Self, TwitterError> {
+        let options = options
+            .split(',')
+            .map(|option| option.trim().to_string())
+            .collect::<Vec<_>>();
+        if options.len() < Self::MIN_OPTIONS || options.len() > Self::MAX_OPTIONS {
+            return Err(TwitterError::Invalid(format!(
+                "A poll needs {} to {} options separated by commas, not {}",
+                Self::MIN_OPTIONS,
+                Self::MAX_OPTIONS,
+                options.len()
+            )));
+        }
+        for (i, option) in options.iter().enumerate() {
+            if option.is_empty() {
+                return Err(TwitterError::Invalid(format!(
+                    "Poll option {} is empty",
+                    i + 1
+                )));
+            }
+            let length = option.chars().count();
+            if length > Self::MAX_OPTION_LENGTH {
+                return Err(TwitterError::Invalid(format!(
+                    "Poll option \"{}\" is {} characters, {} over the limit of {}",
+                    option,
+                    length,
+                    length - Self::MAX_OPTION_LENGTH,
+                    Self::MAX_OPTION_LENGTH
+                )));
+            }
+            if options[..i].contains(option) {
+                return Err(TwitterError::Invalid(format!(
+                    "Poll option \"{}\" is in there twice",
+                    option
+                )));
+            }
+        }
+        if !(Self::MIN_MINUTES..=Self::MAX_MINUTES).contains(&duration_minutes) {
+            return Err(TwitterError::Invalid(format!(
+                "Polls run for {} to {} minutes (7 days), not {}",
+                Self::MIN_MINUTES,
+                Self::MAX_MINUTES,
+                duration_minutes
+            )));
+        }
+        Ok(Self {
+            options,
+            duration_minutes,
+        })
+    }
+}
+
+// Who can reply. Leaving it off means everyone can
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplySettings {
+    MentionedUsers,
+    Following,
+}
+
+impl ReplySettings {
+    // None is everyone
+    pub fn parse(value: &str) -> Result<Option<Self>, TwitterError> {
+        match value.to_lowercase().as_str() {
+            "everyone" => Ok(None),
+            "mentioned" | "mentionedusers" | "mentioned-users" => Ok(Some(Self::MentionedUsers)),
+            "following" => Ok(Some(Self::Following)),
+            _ => Err(TwitterError::Invalid(format!(
+                "Who can reply is everyone, mentioned or following, not {}",
+                value
+            ))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::MentionedUsers => "mentionedUsers",
+            Self::Following => "following",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn polls() {
+        let poll = Poll::new("Cats, Dogs ,Neither", 1440).unwrap();
+        assert_eq!(poll.options, vec!["Cats", "Dogs", "Neither"]);
+        assert!(Poll::new("Just one", 1440).is_err());
+        assert!(Poll::new("a,b,c,d,e", 1440).is_err());
+        assert!(Poll::new("a,,b", 1440).is_err());
+        assert!(Poll::new("a,a", 1440).is_err());
+        assert!(Poll::new("a,this option is way too long to fit", 1440).is_err());
+        assert!(Poll::new("a,b", 4).is_err());
+        assert!(Poll::new("a,b", 10081).is_err());
+    }
+
+    #[test]
+    fn tweet_json() {
+        let tweet = NewTweet {
+            text: String::from("Which?"),
+            poll: Some(Poll::new("a,b", 60).unwrap()),
+            reply_settings: Some(ReplySettings::Following),
+            ..NewTweet::default()
+        };
+        assert_eq!(
+            tweet.json(),
+            json!({
+                "text": "Which?",
+                "poll": { "options": ["a", "b"], "duration_minutes": 60 },
+                "reply_settings": "following",
+            })
+        );
+    }
+}
@@ -1,4 +1,11 @@
+use super::super::credentials::OAuth2Token;
+use super::super::output::{
+    AuthorRecord, MediaRecord, MetricsRecord, RateLimitRecord, TweetRecord, UserRecord,
+};
+use super::RateLimit;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct TwitterCreateResponseData {
@@ -6,6 +13,29 @@ pub struct TwitterCreateResponseData {
     pub text: String,
 }
 
+// https://developer.twitter.com/en/docs/twitter-api/v1/media/upload-media/api-reference/post-media-upload
+// Also what FINALIZE and STATUS send back, which is where `processing_info` shows up
+#[derive(Deserialize, Debug)]
+pub struct MediaUploadResponse {
+    pub media_id_string: String,
+    pub processing_info: Option<MediaProcessingInfo>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MediaProcessingInfo {
+    // pending, in_progress, failed or succeeded
+    pub state: String,
+    pub check_after_secs: Option<u64>,
+    pub progress_percent: Option<u32>,
+    pub error: Option<MediaProcessingError>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MediaProcessingError {
+    pub name: Option<String>,
+    pub message: Option<String>,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct TwitterDeleteResponseData {
     pub deleted: bool,
@@ -22,13 +52,62 @@ pub struct TwitterResponseMeta {
 #[derive(Deserialize, Debug)]
 pub struct TwitterResponse<T> {
     pub data: T,
-    pub meta: Option<TwitterResponseMeta>
+    pub meta: Option<TwitterResponseMeta>,
+    // v2 can return partial errors alongside data, e.g. when a pinned tweet has been deleted
+    pub errors: Option<Vec<TwitterErrorItem>>,
+}
+
+/* Example TwitterProblem (v2):
+{
+    "title": "Forbidden",
+    "detail": "You are not allowed to create a Tweet with duplicate content.",
+    "type": "about:blank",
+    "status": 403
+}
+
+Example TwitterProblem (v1.1):
+{
+    "errors": [{ "code": 187, "message": "Status is a duplicate." }]
+}
+*/
+#[derive(Deserialize, Debug, Default)]
+pub struct TwitterProblem {
+    pub title: Option<String>,
+    pub detail: Option<String>,
+    pub r#type: Option<String>,
+    pub errors: Option<Vec<TwitterErrorItem>>,
+}
+
+// v1.1 items have `code` and `message`, v2 items have `title`, `detail`, `type` and a bunch of resource info
+#[derive(Deserialize, Debug, Clone)]
+pub struct TwitterErrorItem {
+    pub code: Option<i64>,
+    pub message: Option<String>,
+    pub title: Option<String>,
+    pub detail: Option<String>,
+    pub r#type: Option<String>,
+}
+
+impl TwitterErrorItem {
+    pub fn summary(&self) -> String {
+        let text = self
+            .detail
+            .as_ref()
+            .or(self.message.as_ref())
+            .or(self.title.as_ref())
+            .map(String::as_str)
+            .unwrap_or("Unknown error");
+        match self.code {
+            Some(code) => format!("[code {}] {}", code, text),
+            None => text.to_string(),
+        }
+    }
 }
 
 #[derive(Deserialize, Debug)]
 pub struct TwitterFeedUser {
     // id: u64,
-    // id_str: String,
+    id_str: Option<String>,
     // verified: bool,
     name: String,        // display name
     screen_name: String, // handle
@@ -90,30 +169,28 @@ impl TwitterFeedItem {
     pub fn display(&self) {
         println!("---------------------------------\n");
         println!("{}, @{}", self.user.name, self.user.screen_name);
-        match self.retweeted_status {
-            Some(ref retweeted_status) => println!(
+        if let Some(ref retweeted_status) = self.retweeted_status {
+            println!(
                 "Retweeted from: {}, @{}",
                 retweeted_status.user.name, retweeted_status.user.screen_name
-            ),
-            None => (),
+            )
         };
 
         // Future optimization: This doesn't come sequentially in the feed,
         // so a cool future enhancement would be to organize this data such that
         // if a tweet is in reply to another tweet,
         // go and fetch it (or find it in the vec) and print them near each other
-        match self.in_reply_to_screen_name {
-            Some(ref in_reply_to_screen_name) => println!(
+        if let Some(ref in_reply_to_screen_name) = self.in_reply_to_screen_name {
+            println!(
                 "Replied to: {} - https://twitter.com/{}/status/{}",
                 in_reply_to_screen_name,
                 in_reply_to_screen_name,
                 self.in_reply_to_status_id_str
                     .as_ref()
                     .unwrap_or(&"".to_string())
-            ),
-            None => (),
+            )
         };
-        println!("");
+        println!();
 
         // Actual tweet text is in the re-tweet.
         // TODO: not sure about quoted retweets actually 🤔
@@ -122,18 +199,15 @@ impl TwitterFeedItem {
             None => println!("{}", self.text),
         };
 
-        match self.entities.media {
-            Some(ref media) => {
-                println!("");
-                for (i, item) in media.iter().enumerate() {
-                    println!("{} {}: {}", item.r#type, i + 1, item.media_url);
-                }
+        if let Some(ref media) = self.entities.media {
+            println!();
+            for (i, item) in media.iter().enumerate() {
+                println!("{} {}: {}", item.r#type, i + 1, item.media_url);
             }
-            None => (),
         };
 
         // Get those stats
-        println!("");
+        println!();
         println!(
             "{}{} Retweets      {}{} Likes",
             self.retweet_count,
@@ -149,22 +223,86 @@ impl TwitterFeedItem {
     }
 }
 
+impl TwitterFeedItem {
+    pub fn record(&self) -> TweetRecord {
+        TweetRecord {
+            id: self.id_str.to_string(),
+            url: format!(
+                "https://twitter.com/{}/status/{}",
+                self.user.screen_name, self.id_str
+            ),
+            created_at: rfc3339(&self.created_at),
+            // Same as `display`, the text of a retweet is in the original
+            text: match self.retweeted_status {
+                Some(ref retweeted_status) => retweeted_status.text.to_string(),
+                None => self.text.to_string(),
+            },
+            author: AuthorRecord {
+                id: self.user.id_str.clone(),
+                handle: Some(self.user.screen_name.to_string()),
+                name: Some(self.user.name.to_string()),
+            },
+            metrics: MetricsRecord {
+                replies: None,
+                retweets: self.retweet_count as usize,
+                quotes: None,
+                likes: self.favorite_count as usize,
+            },
+            liked: Some(self.favorited),
+            retweeted: Some(self.retweeted),
+            in_reply_to_id: self.in_reply_to_status_id_str.clone(),
+            in_reply_to_handle: self.in_reply_to_screen_name.clone(),
+            retweet_of_handle: self
+                .retweeted_status
+                .as_ref()
+                .map(|status| status.user.screen_name.to_string()),
+            media: self
+                .entities
+                .media
+                .iter()
+                .flatten()
+                .map(|item| MediaRecord {
+                    r#type: item.r#type.to_string(),
+                    url: item.media_url.to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+// v1.1 dates look like "Wed Oct 10 20:19:24 +0000 2018" (always UTC), v2 dates like "2018-10-10T20:19:24.000Z".
+// Records always use the v2 style. Anything unexpected is passed through untouched.
+fn rfc3339(created_at: &str) -> String {
+    let parts: Vec<&str> = created_at.split_whitespace().collect();
+    match parts.as_slice() {
+        [_, month, day, time, "+0000", year] => match MONTHS.iter().position(|m| m == month) {
+            Some(month) => format!("{}-{:02}-{:0>2}T{}.000Z", year, month + 1, day, time),
+            None => created_at.to_string(),
+        },
+        _ => created_at.to_string(),
+    }
+}
+
 pub type TwitterFeed = Vec<TwitterFeedItem>;
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 pub struct PublicMetrics {
-    retweet_count: usize,
-    reply_count: usize,
-    like_count: usize,
-    quote_count: usize,
+    pub retweet_count: usize,
+    pub reply_count: usize,
+    pub like_count: usize,
+    pub quote_count: usize,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 pub struct TwitterHomeItem {
-    text: String,
-    id: String,
-    public_metrics: PublicMetrics,
-    created_at: String,
+    pub text: String,
+    pub id: String,
+    pub public_metrics: PublicMetrics,
+    pub created_at: String,
     author_id: String,
 }
 
@@ -190,7 +328,43 @@ impl TwitterHomeItem {
     }
 }
 
-#[derive(Deserialize, Debug)]
+impl TwitterHomeItem {
+    // v2 only gives us the author's id. Pass the author along when we know who it is (like in `tw home`)
+    pub fn record(&self, author: Option<&TwitterUser>) -> TweetRecord {
+        let author = author.filter(|author| author.id == self.author_id);
+        TweetRecord {
+            id: self.id.to_string(),
+            url: format!(
+                "https://twitter.com/{}/status/{}",
+                author
+                    .map(|author| author.username.as_str())
+                    .unwrap_or(&self.author_id),
+                self.id
+            ),
+            created_at: self.created_at.to_string(),
+            text: self.text.to_string(),
+            author: AuthorRecord {
+                id: Some(self.author_id.to_string()),
+                handle: author.map(|author| author.username.to_string()),
+                name: author.map(|author| author.name.to_string()),
+            },
+            metrics: MetricsRecord {
+                replies: Some(self.public_metrics.reply_count),
+                retweets: self.public_metrics.retweet_count,
+                quotes: Some(self.public_metrics.quote_count),
+                likes: self.public_metrics.like_count,
+            },
+            liked: None,
+            retweeted: None,
+            in_reply_to_id: None,
+            in_reply_to_handle: None,
+            retweet_of_handle: None,
+            media: Vec::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct TwitterUser {
     pub id: String,
     username: String,
@@ -200,27 +374,147 @@ pub struct TwitterUser {
 }
 
 impl TwitterUser {
+    pub fn record(&self) -> UserRecord {
+        UserRecord {
+            id: self.id.to_string(),
+            handle: self.username.to_string(),
+            name: self.name.to_string(),
+            url: format!("https://twitter.com/{}", self.username),
+            created_at: self.created_at.to_string(),
+            pinned_tweet_id: self.pinned_tweet_id.clone(),
+        }
+    }
+
     pub fn display(&self) {
         println!("             ID: {}", self.id);
         println!("       Username: {}", self.username);
         println!("   Display name: {}", self.name);
         println!("Account created: {}", self.created_at);
-        match self.pinned_tweet_id {
-            Some(ref pinned_tweet_id) => println!(
+        if let Some(ref pinned_tweet_id) = self.pinned_tweet_id {
+            println!(
                 "   Pinned tweet: https://twitter.com/{}/status/{}",
                 self.username, pinned_tweet_id
-            ),
-            None => (),
+            )
         };
     }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 pub struct OauthResponse {
     pub access_token: String,
 }
 
+// https://developer.twitter.com/en/docs/authentication/oauth-2-0/user-access-token
+#[derive(Deserialize, Debug)]
+pub struct OAuth2TokenResponse {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    pub expires_in: u64,
+    pub scope: String,
+}
+
+impl From<OAuth2TokenResponse> for OAuth2Token {
+    fn from(response: OAuth2TokenResponse) -> OAuth2Token {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        OAuth2Token {
+            access_token: response.access_token,
+            refresh_token: response.refresh_token,
+            expires_at: now + response.expires_in,
+            scopes: response.scope.split(' ').map(String::from).collect(),
+        }
+    }
+}
+
+// Form encoded response from oauth/request_token and oauth/access_token.
+// `screen_name` is only included in the access token response.
+#[derive(Deserialize, Debug)]
+pub struct OauthToken {
+    pub oauth_token: String,
+    pub oauth_token_secret: String,
+    pub screen_name: Option<String>,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct TwitterLikeResponseData {
     pub liked: bool,
 }
+
+#[derive(Deserialize, Debug)]
+pub struct TwitterRetweetResponseData {
+    pub retweeted: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RateLimitStatusEntry {
+    limit: u32,
+    remaining: u32,
+    reset: u64,
+}
+
+/* Example RateLimitStatus:
+{
+    "rate_limit_context": { "access_token": "..." },
+    "resources": {
+        "statuses": {
+            "/statuses/home_timeline": { "limit": 15, "remaining": 15, "reset": 1403602426 },
+            ...
+        },
+        ...
+    }
+}
+*/
+#[derive(Deserialize, Debug)]
+pub struct RateLimitStatus {
+    resources: BTreeMap<String, BTreeMap<String, RateLimitStatusEntry>>,
+}
+
+impl RateLimitStatusEntry {
+    // Endpoints you haven't touched this window are noise, unless you asked for --all
+    fn is_shown(&self, show_all: bool) -> bool {
+        show_all || self.remaining < self.limit
+    }
+}
+
+impl RateLimitStatus {
+    pub fn records(&self, show_all: bool) -> Vec<RateLimitRecord> {
+        self.resources
+            .iter()
+            .flat_map(|(resource, endpoints)| {
+                endpoints
+                    .iter()
+                    .filter(move |(_, entry)| entry.is_shown(show_all))
+                    .map(move |(endpoint, entry)| RateLimitRecord {
+                        resource: resource.to_string(),
+                        endpoint: endpoint.to_string(),
+                        limit: entry.limit,
+                        remaining: entry.remaining,
+                        reset: entry.reset,
+                    })
+            })
+            .collect()
+    }
+
+    pub fn display(&self, show_all: bool) {
+        for (resource, endpoints) in self.resources.iter() {
+            let endpoints = endpoints
+                .iter()
+                .filter(|(_, entry)| entry.is_shown(show_all))
+                .collect::<Vec<_>>();
+            if endpoints.is_empty() {
+                continue;
+            }
+            println!("{}", resource);
+            for (endpoint, entry) in endpoints {
+                let limit = RateLimit {
+                    limit: entry.limit,
+                    remaining: entry.remaining,
+                    reset: entry.reset,
+                };
+                println!("    {:<50} {}", endpoint, limit);
+            }
+        }
+    }
+}
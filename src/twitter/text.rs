@@ -0,0 +1,366 @@
+// Turning text into tweets: how long a tweet is, and breaking long text up into a thread.
+//
+// Length is counted the way Twitter counts it (https://developer.twitter.com/en/docs/counting-characters):
+//   - the text is NFC normalized first, so é is one character however it was typed
+//   - links count as 23 characters, however long they are, since Twitter swaps them for t.co links
+//   - emoji count as 2, including sequences like 👩‍👩‍👧 or 🇨🇦 that are made of several code points
+//   - Latin, Greek, Cyrillic, Hebrew, Arabic and the like count as 1, everything else (CJK, ...) as 2
+
+use super::super::error::TwitterError;
+use std::ops::Range;
+use unicode_normalization::UnicodeNormalization;
+
+pub const MAX_LENGTH: usize = 280;
+const URL_LENGTH: usize = 23;
+
+// Code points that count as 1, from twitter-text's v3 config
+const LIGHT_RANGES: [(u32, u32); 4] = [
+    (0x0000, 0x10FF),
+    (0x2000, 0x200D),
+    (0x2010, 0x201F),
+    (0x2032, 0x2037),
+];
+
+// Not every TLD there is, just the ones people actually write without an http:// in front
+const TLDS: [&str; 32] = [
+    "com", "org", "net", "edu", "gov", "io", "co", "dev", "app", "ai", "me", "ly", "gg", "tv",
+    "info", "biz", "xyz", "so", "sh", "fm", "to", "uk", "us", "ca", "de", "fr", "jp", "au", "nl",
+    "eu", "in", "ch",
+];
+
+fn in_ranges(c: char, ranges: &[(u32, u32)]) -> bool {
+    ranges
+        .iter()
+        .any(|&(start, end)| (start..=end).contains(&(c as u32)))
+}
+
+fn is_emoji(c: char) -> bool {
+    in_ranges(
+        c,
+        &[
+            (0x1F000, 0x1FAFF), // pictographs, emoticons, transport, flags, ...
+            (0x2600, 0x27BF),   // misc symbols and dingbats
+            (0x2300, 0x23FF),   // ⌚ ⏰ and friends
+            (0x2B00, 0x2BFF),   // ⭐ ⬛
+        ],
+    )
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    in_ranges(c, &[(0x1F1E6, 0x1F1FF)])
+}
+
+// Variation selectors, skin tones, keycaps and tags don't add anything to the emoji they're attached to
+fn is_emoji_modifier(c: char) -> bool {
+    in_ranges(
+        c,
+        &[
+            (0xFE0E, 0xFE0F),
+            (0x1F3FB, 0x1F3FF),
+            (0x20E3, 0x20E3),
+            (0xE0020, 0xE007F),
+        ],
+    )
+}
+
+// Text without any links in it
+fn weigh(text: &str) -> usize {
+    let mut weight = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        // Keycaps (1️⃣) and the like start with a plain character that only becomes an emoji because of what follows
+        let is_sequence = is_emoji(c)
+            || chars
+                .peek()
+                .is_some_and(|&next| next == '\u{FE0F}' || next == '\u{20E3}');
+        if !is_sequence {
+            weight += if in_ranges(c, &LIGHT_RANGES) { 1 } else { 2 };
+            continue;
+        }
+        weight += 2;
+        if is_regional_indicator(c) && chars.peek().copied().is_some_and(is_regional_indicator) {
+            chars.next();
+        }
+        loop {
+            match chars.peek().copied() {
+                Some(next) if is_emoji_modifier(next) => {
+                    chars.next();
+                }
+                // A zero width joiner glues the next emoji onto this one
+                Some('\u{200D}') => {
+                    chars.next();
+                    if chars.peek().copied().is_some_and(is_emoji) {
+                        chars.next();
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+    weight
+}
+
+// Trailing punctuation is almost always the sentence, not the link
+fn trim_url(url: &str) -> &str {
+    url.trim_end_matches(['.', ',', ':', ';', '!', '?', '\'', '"', ')', ']'])
+}
+
+fn is_domain(word: &str) -> bool {
+    let host = word.split(['/', ':', '?', '#']).next().unwrap_or("");
+    let labels = host.split('.').collect::<Vec<_>>();
+    labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+        && TLDS.contains(&labels[labels.len() - 1].to_ascii_lowercase().as_str())
+}
+
+// Byte ranges of everything Twitter will turn into a t.co link
+fn urls(text: &str) -> Vec<Range<usize>> {
+    let mut urls = Vec::new();
+    let mut start = None;
+    for (i, c) in text
+        .char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+    {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some(i),
+            (true, Some(word_start)) => {
+                start = None;
+                let word = &text[word_start..i];
+                let offset = match word.find("https://").or_else(|| word.find("http://")) {
+                    Some(offset) => offset,
+                    None => word.len() - word.trim_start_matches(['(', '[', '"', '\'']).len(),
+                };
+                let candidate = trim_url(&word[offset..]);
+                let is_url = candidate.starts_with("https://")
+                    || candidate.starts_with("http://")
+                    || is_domain(candidate);
+                if is_url && !candidate.is_empty() {
+                    let url_start = word_start + offset;
+                    urls.push(url_start..url_start + candidate.len());
+                }
+            }
+            _ => (),
+        }
+    }
+    urls
+}
+
+pub fn length(text: &str) -> usize {
+    let text = text.nfc().collect::<String>();
+    let mut length = 0;
+    let mut position = 0;
+    for url in urls(&text) {
+        length += weigh(&text[position..url.start]) + URL_LENGTH;
+        position = url.end;
+    }
+    length + weigh(&text[position..])
+}
+
+// "Tweet 2 of 3: 250/280 (30 left)" for every tweet, for drafting
+pub fn summary(tweets: &[String]) -> Vec<String> {
+    tweets
+        .iter()
+        .enumerate()
+        .map(|(i, tweet)| {
+            let which = match tweets.len() {
+                1 => String::new(),
+                total => format!("Tweet {} of {}: ", i + 1, total),
+            };
+            let length = length(tweet);
+            let room = match length <= MAX_LENGTH {
+                true => format!("{} left", MAX_LENGTH - length),
+                false => format!("{} over", length - MAX_LENGTH),
+            };
+            format!("{}{}/{} ({})", which, length, MAX_LENGTH, room)
+        })
+        .collect()
+}
+
+// What's wrong with each tweet of a thread, if anything
+pub fn problems(tweets: &[String]) -> Vec<String> {
+    let mut problems = Vec::new();
+    for (i, tweet) in tweets.iter().enumerate() {
+        let which = match tweets.len() {
+            1 => String::from("Tweet"),
+            total => format!("Tweet {} of {}", i + 1, total),
+        };
+        let length = length(tweet);
+        if tweet.trim().is_empty() {
+            problems.push(format!("{} is empty", which));
+        } else if length > MAX_LENGTH {
+            problems.push(format!(
+                "{} is {} characters, {} over the limit of {}",
+                which,
+                length,
+                length - MAX_LENGTH,
+                MAX_LENGTH
+            ));
+        }
+    }
+    problems
+}
+
+// Every tweet of a thread has to fit, and it's much better to find out before the first one is posted
+pub fn validate(tweets: &[String]) -> Result<(), TwitterError> {
+    let problems = problems(tweets);
+    match problems.is_empty() {
+        true => Ok(()),
+        false => Err(TwitterError::Invalid(format!(
+            "{}. Nothing was posted. Try --split?",
+            problems.join(", ")
+        ))),
+    }
+}
+
+// The byte index of the longest prefix of `text` that fits in `max`
+fn fitting_prefix(text: &str, max: usize) -> usize {
+    let boundaries = text
+        .char_indices()
+        .map(|(i, _)| i)
+        .skip(1)
+        .chain(std::iter::once(text.len()))
+        .collect::<Vec<_>>();
+    let fits = boundaries.partition_point(|&end| length(&text[..end]) <= max);
+    match fits {
+        0 => 0,
+        fits => boundaries[fits - 1],
+    }
+}
+
+// Where to cut `text` so the first part is at most `max` long: the end of the last sentence if there's one in the
+// second half, otherwise the last space, otherwise wherever it has to
+fn cut(text: &str, max: usize) -> usize {
+    let limit = fitting_prefix(text, max);
+    let mut sentence = None;
+    let mut word = None;
+    let mut previous = ' ';
+    for (i, c) in text[..limit].char_indices() {
+        if i > 0 && c.is_whitespace() {
+            word = Some(i);
+            if c == '\n' || matches!(previous, '.' | '!' | '?' | '…') {
+                sentence = Some(i);
+            }
+        }
+        previous = c;
+    }
+    // The character right after the prefix counts too, the prefix could end on a sentence
+    if let Some(next) = text[limit..].chars().next() {
+        if next.is_whitespace() {
+            word = Some(limit);
+            if next == '\n' || matches!(previous, '.' | '!' | '?' | '…') {
+                sentence = Some(limit);
+            }
+        }
+    }
+    match (sentence, word) {
+        (Some(sentence), _) if sentence >= limit / 2 => sentence,
+        (_, Some(word)) => word,
+        // One enormous word. Still has to be cut somewhere
+        _ => limit.max(text.chars().next().map_or(0, char::len_utf8)),
+    }
+}
+
+// Breaks `text` into pieces that are each at most `max` long, on sentence or word boundaries where possible
+pub fn split(text: &str, max: usize) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut rest = text.trim();
+    while length(rest) > max {
+        let at = cut(rest, max);
+        pieces.push(rest[..at].trim_end().to_string());
+        rest = rest[at..].trim_start();
+    }
+    if !rest.is_empty() {
+        pieces.push(rest.to_string());
+    }
+    pieces
+}
+
+fn numbering(i: usize, total: usize) -> String {
+    format!(" {}/{}", i, total)
+}
+
+// Adds " 1/n", " 2/n", ... to the end of every tweet
+pub fn number(tweets: Vec<String>) -> Vec<String> {
+    let total = tweets.len();
+    tweets
+        .into_iter()
+        .enumerate()
+        .map(|(i, tweet)| format!("{}{}", tweet, numbering(i + 1, total)))
+        .collect()
+}
+
+// Splits every segment of a thread that's too long, making room for the numbering if there's going to be any.
+// How much room that takes depends on how many tweets there end up being, so keep going until that settles down.
+pub fn split_thread(segments: &[String], max: usize, numbered: bool) -> Vec<String> {
+    let mut reserved = match numbered {
+        true => length(&numbering(9, 9)),
+        false => 0,
+    };
+    loop {
+        let tweets = segments
+            .iter()
+            .flat_map(|segment| split(segment, max - reserved))
+            .collect::<Vec<_>>();
+        let needed = length(&numbering(tweets.len(), tweets.len()));
+        // 1/1 is just noise
+        if !numbered || tweets.len() == 1 {
+            return tweets;
+        } else if needed <= reserved {
+            return number(tweets);
+        }
+        reserved = needed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_text_is_left_alone() {
+        assert_eq!(split("  hello  ", 280), vec!["hello"]);
+        assert!(split("", 280).is_empty());
+    }
+
+    #[test]
+    fn splits_on_sentences_then_words() {
+        assert_eq!(
+            split("One two three. Four five six seven.", 20),
+            vec!["One two three.", "Four five six seven."]
+        );
+        assert_eq!(
+            split("alpha beta gamma delta", 11),
+            vec!["alpha beta", "gamma delta"]
+        );
+        assert_eq!(split("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+    }
+
+    #[test]
+    fn weighted_length() {
+        assert_eq!(length("hello"), 5);
+        // é typed as e + combining accent is still one character
+        assert_eq!(length("cafe\u{301}"), 4);
+        assert_eq!(length("日本語"), 6);
+        assert_eq!(length("👍"), 2);
+        assert_eq!(length("👍🏽"), 2);
+        assert_eq!(length("👩\u{200D}👩\u{200D}👧"), 2);
+        assert_eq!(length("🇨🇦"), 2);
+        assert_eq!(length("1\u{FE0F}\u{20E3}"), 2);
+        assert_eq!(
+            length("see https://example.com/a/very/long/path/indeed."),
+            4 + 23 + 1
+        );
+        assert_eq!(length("(example.com)"), 1 + 23 + 1);
+        assert_eq!(length("e.g. not.a.link"), 15);
+    }
+
+    #[test]
+    fn numbering_fits() {
+        let text = "word ".repeat(100);
+        let tweets = split_thread(&[text], 50, true);
+        assert!(tweets.iter().all(|tweet| length(tweet) <= 50));
+        assert!(tweets[0].ends_with(&format!(" 1/{}", tweets.len())));
+    }
+}